@@ -0,0 +1,93 @@
+const ACHIEVEMENT_KINDS: usize = 5;
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum Achievement {
+    FirstBite,
+    StraightShooter,
+    ComboFive,
+    Flawless,
+    Marathon,
+}
+
+pub const ALL_ACHIEVEMENTS: [Achievement; ACHIEVEMENT_KINDS] = [
+    Achievement::FirstBite, Achievement::StraightShooter, Achievement::ComboFive,
+    Achievement::Flawless, Achievement::Marathon
+];
+
+impl Achievement {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::FirstBite => "First Bite",
+            Achievement::StraightShooter => "Straight Shooter",
+            Achievement::ComboFive => "Combo x5",
+            Achievement::Flawless => "Flawless",
+            Achievement::Marathon => "Marathon"
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Achievement::FirstBite => "Eat your first food",
+            Achievement::StraightShooter => "Eat 10 food without turning",
+            Achievement::ComboFive => "Reach a combo of 5",
+            Achievement::Flawless => "Win without losing a life",
+            Achievement::Marathon => "Eat 50 food in one run"
+        }
+    }
+
+    fn bit(&self) -> u32 {
+        1 << match self {
+            Achievement::FirstBite => 0,
+            Achievement::StraightShooter => 1,
+            Achievement::ComboFive => 2,
+            Achievement::Flawless => 3,
+            Achievement::Marathon => 4
+        }
+    }
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct Achievements {
+    unlocked: u32
+}
+
+impl Default for Achievements {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Achievements {
+    pub fn new() -> Self {
+        Achievements {unlocked: 0}
+    }
+
+    // Unknown bits from a newer save are dropped.
+    pub fn from_bits(bits: u32) -> Self {
+        let known = ALL_ACHIEVEMENTS.iter().fold(0, |mask, a| mask | a.bit());
+        Achievements {unlocked: bits & known}
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.unlocked
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked & achievement.bit() != 0
+    }
+
+    // True only the first time, so the caller knows when to announce it.
+    pub fn unlock(&mut self, achievement: Achievement) -> bool {
+        let fresh = !self.is_unlocked(achievement);
+        self.unlocked |= achievement.bit();
+        fresh
+    }
+
+    pub fn merge(&mut self, other: Achievements) {
+        self.unlocked |= other.unlocked;
+    }
+
+    pub fn unlocked(&self) -> impl Iterator<Item=Achievement> + '_ {
+        ALL_ACHIEVEMENTS.iter().copied().filter(move |a| self.is_unlocked(*a))
+    }
+}
@@ -0,0 +1,193 @@
+use x86_64::instructions::port::Port;
+
+pub const SECTOR_SIZE: usize = 512;
+
+const PRIMARY_IO: u16 = 0x1F0;
+const PRIMARY_CONTROL: u16 = 0x3F6;
+
+// Offsets from the I/O base.
+const REG_DATA: u16 = 0;
+const REG_ERROR: u16 = 1;
+const REG_SECTOR_COUNT: u16 = 2;
+const REG_LBA_LOW: u16 = 3;
+const REG_LBA_MID: u16 = 4;
+const REG_LBA_HIGH: u16 = 5;
+const REG_DRIVE: u16 = 6;
+const REG_STATUS: u16 = 7;
+const REG_COMMAND: u16 = 7;
+
+// Keeps the drive from raising IRQ 14, which nothing handles; every
+// transfer is polled.
+const CONTROL_NIEN: u8 = 0x02;
+
+const STATUS_ERR: u8 = 0x01;
+const STATUS_DRQ: u8 = 0x08;
+const STATUS_DF: u8 = 0x20;
+const STATUS_BSY: u8 = 0x80;
+
+const CMD_READ_SECTORS: u8 = 0x20;
+const CMD_WRITE_SECTORS: u8 = 0x30;
+const CMD_CACHE_FLUSH: u8 = 0xE7;
+const CMD_IDENTIFY: u8 = 0xEC;
+
+// Polls of the status register before giving up on the drive.
+const POLL_LIMIT: u32 = 100_000;
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum AtaError {
+    NoDrive,
+    Timeout,
+    DeviceFault,
+    Failed(u8),
+}
+
+// One drive on a PIO bus, addressed with LBA28. Whether it exists is
+// found out by IDENTIFY on first use and remembered.
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct AtaDrive {
+    io: u16,
+    control: u16,
+    slave: bool,
+    present: Option<bool>,
+}
+
+impl AtaDrive {
+    pub const fn new(io: u16, control: u16, slave: bool) -> Self {
+        AtaDrive {io, control, slave, present: None}
+    }
+
+    pub const fn primary_slave() -> Self {
+        Self::new(PRIMARY_IO, PRIMARY_CONTROL, true)
+    }
+
+    pub fn identify(&mut self) -> Result<(), AtaError> {
+        unsafe {
+            Port::<u8>::new(self.control).write(CONTROL_NIEN);
+        }
+        self.select(0);
+        for reg in [REG_SECTOR_COUNT, REG_LBA_LOW, REG_LBA_MID, REG_LBA_HIGH].iter() {
+            self.write_reg(*reg, 0);
+        }
+        self.write_reg(REG_COMMAND, CMD_IDENTIFY);
+        // A floating bus reads back all ones; an empty slot reads zero.
+        match self.read_reg(REG_STATUS) {
+            0 | 0xFF => return Err(AtaError::NoDrive),
+            _ => {}
+        }
+        self.wait_not_busy()?;
+        // ATAPI and SATA devices leave a signature here; only plain ATA disks are used.
+        if self.read_reg(REG_LBA_MID) != 0 || self.read_reg(REG_LBA_HIGH) != 0 {
+            return Err(AtaError::NoDrive);
+        }
+        self.wait_data()?;
+        for _ in 0..SECTOR_SIZE / 2 {
+            self.read_data();
+        }
+        Ok(())
+    }
+
+    pub fn read_sector(&mut self, lba: u32, buf: &mut [u8; SECTOR_SIZE]) -> Result<(), AtaError> {
+        self.send(lba, CMD_READ_SECTORS)?;
+        self.wait_data()?;
+        for pair in buf.chunks_exact_mut(2) {
+            pair.copy_from_slice(&self.read_data().to_le_bytes());
+        }
+        Ok(())
+    }
+
+    pub fn write_sector(&mut self, lba: u32, buf: &[u8; SECTOR_SIZE]) -> Result<(), AtaError> {
+        self.send(lba, CMD_WRITE_SECTORS)?;
+        self.wait_data()?;
+        for pair in buf.chunks_exact(2) {
+            self.write_data(u16::from_le_bytes([pair[0], pair[1]]));
+        }
+        self.write_reg(REG_COMMAND, CMD_CACHE_FLUSH);
+        self.wait_not_busy()
+    }
+
+    fn send(&mut self, lba: u32, command: u8) -> Result<(), AtaError> {
+        assert!(lba < 1 << 28, "LBA28 cannot address sector {}", lba);
+        self.ensure_present()?;
+        self.wait_not_busy()?;
+        self.select(lba);
+        self.write_reg(REG_SECTOR_COUNT, 1);
+        self.write_reg(REG_LBA_LOW, lba as u8);
+        self.write_reg(REG_LBA_MID, (lba >> 8) as u8);
+        self.write_reg(REG_LBA_HIGH, (lba >> 16) as u8);
+        self.write_reg(REG_COMMAND, command);
+        Ok(())
+    }
+
+    fn ensure_present(&mut self) -> Result<(), AtaError> {
+        match self.present {
+            Some(true) => Ok(()),
+            Some(false) => Err(AtaError::NoDrive),
+            None => {
+                let found = self.identify();
+                self.present = Some(found.is_ok());
+                found
+            }
+        }
+    }
+
+    fn select(&mut self, lba: u32) {
+        let drive = 0xE0 | (self.slave as u8) << 4 | ((lba >> 24) & 0x0F) as u8;
+        self.write_reg(REG_DRIVE, drive);
+        // Four reads of the alternate status take the 400ns a drive
+        // needs to switch.
+        for _ in 0..4 {
+            unsafe {
+                Port::<u8>::new(self.control).read();
+            }
+        }
+    }
+
+    fn wait_not_busy(&mut self) -> Result<(), AtaError> {
+        for _ in 0..POLL_LIMIT {
+            let status = self.read_reg(REG_STATUS);
+            if status & STATUS_BSY == 0 {
+                return self.check(status);
+            }
+        }
+        Err(AtaError::Timeout)
+    }
+
+    fn wait_data(&mut self) -> Result<(), AtaError> {
+        for _ in 0..POLL_LIMIT {
+            let status = self.read_reg(REG_STATUS);
+            if status & STATUS_BSY == 0 {
+                self.check(status)?;
+                if status & STATUS_DRQ != 0 {
+                    return Ok(());
+                }
+            }
+        }
+        Err(AtaError::Timeout)
+    }
+
+    fn check(&mut self, status: u8) -> Result<(), AtaError> {
+        if status & STATUS_DF != 0 {
+            Err(AtaError::DeviceFault)
+        } else if status & STATUS_ERR != 0 {
+            Err(AtaError::Failed(self.read_reg(REG_ERROR)))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read_reg(&mut self, reg: u16) -> u8 {
+        unsafe {Port::<u8>::new(self.io + reg).read()}
+    }
+
+    fn write_reg(&mut self, reg: u16, value: u8) {
+        unsafe {Port::<u8>::new(self.io + reg).write(value)}
+    }
+
+    fn read_data(&mut self) -> u16 {
+        unsafe {Port::<u16>::new(self.io + REG_DATA).read()}
+    }
+
+    fn write_data(&mut self, value: u16) {
+        unsafe {Port::<u16>::new(self.io + REG_DATA).write(value)}
+    }
+}
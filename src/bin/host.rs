@@ -0,0 +1,133 @@
+// Plays the game in a terminal on the development machine, without
+// booting QEMU. See the host feature in Cargo.toml for how to run it.
+// Ctrl-C quits.
+
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+use crossterm::{cursor, event, queue, execute, style, terminal};
+use crossterm::event::{Event, KeyEvent, KeyModifiers};
+use pc_keyboard::{DecodedKey, KeyCode};
+use pluggable_interrupt_os::vga_buffer::{BUFFER_WIDTH, BUFFER_HEIGHT, Color};
+use baremetal_snake::{SnakeGame, MainGame, GameConfig, Renderer, ColorCode};
+
+// The PIT's power-on rate, which the game's timing assumes.
+const TICK: Duration = Duration::from_micros(54_925);
+
+// Indexed by the VGA colour number.
+const TERMINAL_COLORS: [style::Color; 16] = [
+    style::Color::Black, style::Color::DarkBlue, style::Color::DarkGreen, style::Color::DarkCyan,
+    style::Color::DarkRed, style::Color::DarkMagenta, style::Color::DarkYellow, style::Color::Grey,
+    style::Color::DarkGrey, style::Color::Blue, style::Color::Green, style::Color::Cyan,
+    style::Color::Red, style::Color::Magenta, style::Color::Yellow, style::Color::White
+];
+
+fn terminal_color(color: Color) -> style::Color {
+    TERMINAL_COLORS[color as usize]
+}
+
+// Drawing goes into a screen-sized grid that is written out once per
+// tick.
+struct TerminalScreen {
+    cells: [[(char, ColorCode); BUFFER_WIDTH]; BUFFER_HEIGHT],
+    out: Stdout,
+}
+
+impl TerminalScreen {
+    fn new() -> Self {
+        TerminalScreen {cells: [[(' ', ColorCode::new(Color::White, Color::Black)); BUFFER_WIDTH]; BUFFER_HEIGHT], out: io::stdout()}
+    }
+
+    fn flush(&mut self) -> crossterm::Result<()> {
+        let mut current = None;
+        for (row, line) in self.cells.iter().enumerate() {
+            queue!(self.out, cursor::MoveTo(0, row as u16))?;
+            for (c, color) in line.iter() {
+                if current != Some(*color) {
+                    queue!(self.out,
+                        style::SetForegroundColor(terminal_color(color.foreground())),
+                        style::SetBackgroundColor(terminal_color(color.background())))?;
+                    current = Some(*color);
+                }
+                queue!(self.out, style::Print(*c))?;
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+impl Renderer for TerminalScreen {
+    fn plot(&mut self, c: char, col: usize, row: usize, color: ColorCode) {
+        if row < BUFFER_HEIGHT && col < BUFFER_WIDTH {
+            self.cells[row][col] = (c, color);
+        }
+    }
+
+    fn plot_str(&mut self, s: &str, col: usize, row: usize, color: ColorCode) {
+        for (i, c) in s.chars().enumerate() {
+            self.plot(c, col + i, row, color);
+        }
+    }
+
+    fn clear_row(&mut self, row: usize, background: Color) {
+        for col in 0..BUFFER_WIDTH {
+            self.plot(' ', col, row, ColorCode::new(Color::White, background));
+        }
+    }
+}
+
+// The same keys the game gets from pc-keyboard on real hardware.
+fn decode(key: KeyEvent) -> Option<DecodedKey> {
+    use crossterm::event::KeyCode as Term;
+    match key.code {
+        Term::Up => Some(DecodedKey::RawKey(KeyCode::ArrowUp)),
+        Term::Down => Some(DecodedKey::RawKey(KeyCode::ArrowDown)),
+        Term::Left => Some(DecodedKey::RawKey(KeyCode::ArrowLeft)),
+        Term::Right => Some(DecodedKey::RawKey(KeyCode::ArrowRight)),
+        Term::Enter => Some(DecodedKey::Unicode('\n')),
+        Term::Backspace => Some(DecodedKey::Unicode('\u{8}')),
+        Term::Esc => Some(DecodedKey::Unicode('\u{1b}')),
+        Term::F(5) => Some(DecodedKey::RawKey(KeyCode::F5)),
+        Term::F(9) => Some(DecodedKey::RawKey(KeyCode::F9)),
+        Term::F(12) => Some(DecodedKey::RawKey(KeyCode::F12)),
+        Term::Char(c) => Some(DecodedKey::Unicode(c)),
+        _ => None
+    }
+}
+
+fn run(game: &mut MainGame, screen: &mut TerminalScreen) -> crossterm::Result<()> {
+    let mut next_tick = Instant::now();
+    loop {
+        let now = Instant::now();
+        if now >= next_tick {
+            baremetal_snake::tick_with_renderer(screen, game);
+            if let Some(save) = game.take_pending_save() {
+                let _ = baremetal_snake::write_save(&save);
+            }
+            screen.flush()?;
+            next_tick += TICK;
+            continue;
+        }
+        if event::poll(next_tick - now)? {
+            if let Event::Key(key) = event::read()? {
+                if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == event::KeyCode::Char('c') {
+                    return Ok(());
+                }
+                if let Some(key) = decode(key) {
+                    game.key(key);
+                }
+            }
+        }
+    }
+}
+
+fn main() -> crossterm::Result<()> {
+    let mut game: MainGame = SnakeGame::new(GameConfig::default());
+    let mut screen = TerminalScreen::new();
+    terminal::enable_raw_mode()?;
+    execute!(screen.out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = run(&mut game, &mut screen);
+    execute!(screen.out, style::ResetColor, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
@@ -0,0 +1,63 @@
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct Stage {
+    pub map: &'static str,
+    pub food_goal: u32,
+}
+
+// Only the first stage starts unlocked; finishing a stage opens the next
+// one for the rest of the session.
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct Campaign {
+    stages: &'static [Stage],
+    unlocked: usize,
+    selected: usize,
+}
+
+impl Campaign {
+    pub fn new(stages: &'static [Stage]) -> Self {
+        Campaign {stages, unlocked: stages.len().min(1), selected: 0}
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    pub fn stage(&self, i: usize) -> Option<Stage> {
+        self.stages.get(i).copied()
+    }
+
+    pub fn unlocked(&self) -> usize {
+        self.unlocked
+    }
+
+    pub fn is_unlocked(&self, i: usize) -> bool {
+        i < self.unlocked
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.unlocked {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    // Restores progress from a save; the first stage is always open.
+    pub fn unlock(&mut self, count: usize) {
+        self.unlocked = self.unlocked.max(count.min(self.stages.len()));
+    }
+
+    pub fn complete(&mut self, i: usize) {
+        self.unlocked = self.unlocked.max((i + 2).min(self.stages.len()));
+    }
+}
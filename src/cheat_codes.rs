@@ -0,0 +1,123 @@
+use pc_keyboard::{DecodedKey, KeyCode};
+use pluggable_interrupt_os::vga_buffer::Color;
+use crate::{SnakeGame, Position, MAX_LIVES};
+
+// Longest code that can be recognised.
+const HISTORY_LEN: usize = 16;
+const EXTRA_LIVES: u32 = 3;
+const CHEAT_TOAST_FRAMES: u64 = 60;
+const RAINBOW: [Color; 6] = [Color::LightRed, Color::Yellow, Color::LightGreen, Color::LightCyan, Color::LightBlue, Color::LightMagenta];
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum CheatEffect {
+    ExtraLives,
+    RainbowSnake,
+}
+
+impl CheatEffect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CheatEffect::ExtraLives => "Extra lives",
+            CheatEffect::RainbowSnake => "Rainbow snake"
+        }
+    }
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct CheatCode {
+    pub keys: &'static [DecodedKey],
+    pub effect: CheatEffect,
+}
+
+const KONAMI: [DecodedKey; 10] = [
+    DecodedKey::RawKey(KeyCode::ArrowUp), DecodedKey::RawKey(KeyCode::ArrowUp),
+    DecodedKey::RawKey(KeyCode::ArrowDown), DecodedKey::RawKey(KeyCode::ArrowDown),
+    DecodedKey::RawKey(KeyCode::ArrowLeft), DecodedKey::RawKey(KeyCode::ArrowRight),
+    DecodedKey::RawKey(KeyCode::ArrowLeft), DecodedKey::RawKey(KeyCode::ArrowRight),
+    DecodedKey::Unicode('b'), DecodedKey::Unicode('a')
+];
+
+const RAINBOW_WORD: [DecodedKey; 7] = [
+    DecodedKey::Unicode('r'), DecodedKey::Unicode('a'), DecodedKey::Unicode('i'), DecodedKey::Unicode('n'),
+    DecodedKey::Unicode('b'), DecodedKey::Unicode('o'), DecodedKey::Unicode('w')
+];
+
+pub const DEFAULT_CHEAT_CODES: [CheatCode; 2] = [
+    CheatCode {keys: &KONAMI, effect: CheatEffect::ExtraLives},
+    CheatCode {keys: &RAINBOW_WORD, effect: CheatEffect::RainbowSnake},
+];
+
+// Keeps the latest keys rather than a position per code, so a code still
+// matches straight after a false start that shares its opening keys.
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub(crate) struct SequenceMatcher {
+    history: [DecodedKey; HISTORY_LEN],
+    len: usize,
+}
+
+impl SequenceMatcher {
+    pub(crate) fn new() -> Self {
+        SequenceMatcher {history: [DecodedKey::Unicode(' '); HISTORY_LEN], len: 0}
+    }
+
+    // Letters match regardless of shift.
+    pub(crate) fn push(&mut self, key: DecodedKey) {
+        let key = match key {
+            DecodedKey::Unicode(c) => DecodedKey::Unicode(c.to_ascii_lowercase()),
+            raw => raw
+        };
+        if self.len == HISTORY_LEN {
+            self.history.copy_within(1.., 0);
+            self.len -= 1;
+        }
+        self.history[self.len] = key;
+        self.len += 1;
+    }
+
+    pub(crate) fn ends_with(&self, keys: &[DecodedKey]) -> bool {
+        !keys.is_empty() && keys.len() <= self.len && self.history[self.len - keys.len()..self.len] == *keys
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    pub fn set_cheat_codes(&mut self, codes: &'static [CheatCode]) {
+        self.cheat_codes = codes;
+        self.code_matcher.clear();
+    }
+
+    pub fn is_rainbow(&self) -> bool {
+        self.rainbow
+    }
+
+    // Codes are only listened for on the title screen, where none of
+    // their keys do anything else.
+    pub(crate) fn check_cheat_codes(&mut self, key: DecodedKey) {
+        self.code_matcher.push(key);
+        let found = self.cheat_codes.iter().find(|code| self.code_matcher.ends_with(code.keys)).copied();
+        if let Some(code) = found {
+            self.code_matcher.clear();
+            self.apply_cheat(code.effect);
+            self.show_toast_parts(&["Cheat: ", code.effect.name()], CHEAT_TOAST_FRAMES);
+        }
+    }
+
+    fn apply_cheat(&mut self, effect: CheatEffect) {
+        match effect {
+            CheatEffect::ExtraLives => {
+                self.config.lives = (self.config.lives + EXTRA_LIVES).min(MAX_LIVES as u32);
+                self.lives = self.config.lives;
+            }
+            CheatEffect::RainbowSnake => self.rainbow = !self.rainbow
+        }
+    }
+
+    // Stripes run diagonally and drift a little each frame.
+    pub(crate) fn rainbow_color(&self, p: Position<WIDTH,HEIGHT>) -> Color {
+        let (row, col) = p.row_col();
+        RAINBOW[(row + col + self.frames as usize) % RAINBOW.len()]
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(target_os = "none")]
+use x86_64::instructions::interrupts;
+#[cfg(target_os = "none")]
+use x86_64::instructions::port::Port;
+use crate::HighScore;
+
+#[cfg(target_os = "none")]
+const CMOS_INDEX: u16 = 0x70;
+#[cfg(target_os = "none")]
+const CMOS_DATA: u16 = 0x71;
+// Keeps NMIs masked while a register is selected.
+#[cfg(target_os = "none")]
+const NMI_DISABLE: u8 = 0x80;
+// Neither the PC BIOS layout nor QEMU's use registers 0x70-0x75.
+const SAVE_BASE: u8 = 0x70;
+// Score (4 bytes, little-endian), level, checksum.
+const SAVE_LEN: usize = 6;
+// Seeding the checksum means zeroed NVRAM does not pass as a save.
+const CHECKSUM_SEED: u8 = 0x5A;
+
+#[cfg(target_os = "none")]
+pub fn read_register(reg: u8) -> u8 {
+    interrupts::without_interrupts(|| unsafe {
+        Port::<u8>::new(CMOS_INDEX).write(NMI_DISABLE | reg);
+        Port::<u8>::new(CMOS_DATA).read()
+    })
+}
+
+#[cfg(target_os = "none")]
+pub fn write_register(reg: u8, value: u8) {
+    interrupts::without_interrupts(|| unsafe {
+        Port::<u8>::new(CMOS_INDEX).write(NMI_DISABLE | reg);
+        Port::<u8>::new(CMOS_DATA).write(value);
+    })
+}
+
+// A host process may not touch I/O ports. Reading zeros never passes the
+// checksum, so it looks like a machine with nothing saved.
+#[cfg(not(target_os = "none"))]
+pub fn read_register(_reg: u8) -> u8 {
+    0
+}
+
+#[cfg(not(target_os = "none"))]
+pub fn write_register(_reg: u8, _value: u8) {}
+
+pub fn load_best() -> Option<HighScore> {
+    let mut bytes = [0; SAVE_LEN];
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = read_register(SAVE_BASE + i as u8);
+    }
+    decode_best(&bytes)
+}
+
+pub fn save_best(best: HighScore) {
+    for (i, b) in encode_best(best).iter().enumerate() {
+        write_register(SAVE_BASE + i as u8, *b);
+    }
+}
+
+fn encode_best(best: HighScore) -> [u8; SAVE_LEN] {
+    let mut bytes = [0; SAVE_LEN];
+    bytes[..4].copy_from_slice(&best.score.to_le_bytes());
+    bytes[4] = best.level.min(u8::MAX as usize) as u8;
+    bytes[SAVE_LEN - 1] = checksum(&bytes[..SAVE_LEN - 1]);
+    bytes
+}
+
+// None when nothing was saved yet or the bytes fail their checksum.
+fn decode_best(bytes: &[u8; SAVE_LEN]) -> Option<HighScore> {
+    if checksum(&bytes[..SAVE_LEN - 1]) != bytes[SAVE_LEN - 1] {
+        return None;
+    }
+    let score = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    Some(HighScore {score, level: bytes[4] as usize}).filter(|best| best.score > 0)
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(CHECKSUM_SEED, |sum, b| sum.rotate_left(1) ^ *b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEST: HighScore = HighScore {score: 12_345, level: 3};
+
+    #[test]
+    fn a_best_score_decodes_to_itself() {
+        assert_eq!(decode_best(&encode_best(BEST)), Some(BEST));
+    }
+
+    #[test]
+    fn a_bad_checksum_is_refused() {
+        let mut bytes = encode_best(BEST);
+        bytes[SAVE_LEN - 1] ^= 1;
+        assert_eq!(decode_best(&bytes), None);
+        let mut bytes = encode_best(BEST);
+        bytes[0] ^= 1;
+        assert_eq!(decode_best(&bytes), None);
+    }
+
+    #[test]
+    fn blank_nvram_is_not_a_save() {
+        assert_eq!(decode_best(&[0; SAVE_LEN]), None);
+        assert_eq!(decode_best(&[0xFF; SAVE_LEN]), None);
+        assert_eq!(decode_best(&encode_best(HighScore {score: 0, level: 1})), None);
+    }
+
+    #[test]
+    fn levels_past_a_byte_are_clamped() {
+        let best = HighScore {score: 7, level: 1_000};
+        assert_eq!(decode_best(&encode_best(best)), Some(HighScore {score: 7, level: 255}));
+    }
+}
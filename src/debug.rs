@@ -0,0 +1,188 @@
+use pc_keyboard::DecodedKey;
+use crate::{SnakeGame, MainGame, Renderer};
+#[cfg(feature = "debug-tools")]
+use pc_keyboard::KeyCode;
+#[cfg(feature = "debug-tools")]
+use pluggable_interrupt_os::vga_buffer::{BUFFER_WIDTH, Color};
+#[cfg(feature = "debug-tools")]
+use crate::{ColorCode, Status, OverCause, HEADER_SPACE};
+
+#[cfg(feature = "debug-tools")]
+const OVERLAY_WIDTH: usize = 24;
+
+// Developer tools behind F12. None of this is built unless the
+// debug-tools feature is on, so players never get it.
+#[cfg(feature = "debug-tools")]
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub(crate) struct DebugOverlay {
+    open: bool,
+    god_mode: bool,
+    frame_step: bool,
+    step_pending: bool,
+    dump: bool,
+}
+
+#[cfg(feature = "debug-tools")]
+impl DebugOverlay {
+    pub(crate) fn new() -> Self {
+        DebugOverlay {open: false, god_mode: false, frame_step: false, step_pending: false, dump: false}
+    }
+}
+
+#[cfg(feature = "debug-tools")]
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    pub fn debug_overlay_open(&self) -> bool {
+        self.debug.open
+    }
+
+    pub(crate) fn is_god_mode(&self) -> bool {
+        self.debug.god_mode
+    }
+
+    // While frame-stepping the game, its clock included, stands still
+    // until the next step is asked for.
+    pub(crate) fn debug_hold(&mut self) -> bool {
+        if !self.debug.frame_step {
+            false
+        } else if self.debug.step_pending {
+            self.debug.step_pending = false;
+            false
+        } else {
+            true
+        }
+    }
+
+    pub(crate) fn debug_frozen(&self) -> bool {
+        self.debug.frame_step && !self.debug.step_pending
+    }
+
+    // Returns true when the key was meant for the overlay; anything else
+    // still reaches the game, so it can be steered with the overlay open.
+    pub(crate) fn debug_key(&mut self, key: DecodedKey) -> bool {
+        if key == DecodedKey::RawKey(KeyCode::F12) {
+            self.debug.open = !self.debug.open;
+            return true;
+        }
+        if !self.debug.open {
+            return false;
+        }
+        match key {
+            DecodedKey::RawKey(KeyCode::G) | DecodedKey::Unicode('g') => self.debug.god_mode = !self.debug.god_mode,
+            DecodedKey::RawKey(KeyCode::F) | DecodedKey::Unicode('f') => {
+                self.debug.frame_step = !self.debug.frame_step;
+                self.debug.step_pending = false;
+            }
+            DecodedKey::RawKey(KeyCode::Spacebar) | DecodedKey::Unicode(' ') => self.debug.step_pending = true,
+            DecodedKey::RawKey(KeyCode::K) | DecodedKey::Unicode('k') if self.status == Status::Playing => {
+                self.end_game(OverCause::Crashed);
+            }
+            DecodedKey::RawKey(KeyCode::N) | DecodedKey::Unicode('n') if self.status == Status::Playing => {
+                self.spawn_food();
+            }
+            DecodedKey::RawKey(KeyCode::X) | DecodedKey::Unicode('x') => self.debug.dump = !self.debug.dump,
+            _ => return false
+        }
+        true
+    }
+}
+
+#[cfg(feature = "debug-tools")]
+pub(crate) fn draw_overlay<R: Renderer>(r: &mut R, game: &MainGame) {
+    if !game.debug.open {
+        return;
+    }
+    let color = ColorCode::new(Color::White, Color::DarkGray);
+    let on_color = ColorCode::new(Color::Yellow, Color::DarkGray);
+    let col = BUFFER_WIDTH - OVERLAY_WIDTH;
+    let toggles: [(&str, bool); 3] = [
+        ("G: god mode", game.debug.god_mode),
+        ("F: frame step", game.debug.frame_step),
+        ("X: state dump", game.debug.dump),
+    ];
+    let actions = ["Space: step", "K: end game", "N: plant food"];
+    let mut row = HEADER_SPACE;
+    let line = |r: &mut R, text: &str, color: ColorCode, row: &mut usize| {
+        for i in 0..OVERLAY_WIDTH {
+            r.plot(' ', col + i, *row, color);
+        }
+        r.plot_str(text, col + 1, *row, color);
+        *row += 1;
+    };
+    line(r, "DEBUG (F12)", on_color, &mut row);
+    for (text, on) in toggles.iter() {
+        line(r, text, if *on {on_color} else {color}, &mut row);
+    }
+    for text in actions.iter() {
+        line(r, text, color, &mut row);
+    }
+    if game.debug.dump {
+        let (head_row, head_col) = game.snake_at().row_col();
+        let values: [(&str, isize); 8] = [
+            ("head row", head_row as isize),
+            ("head col", head_col as isize),
+            ("length", game.length() as isize),
+            ("score", game.score() as isize),
+            ("food", game.food_eaten as isize),
+            ("lives", game.lives as isize),
+            ("ticks", game.elapsed_ticks as isize),
+            ("seed", game.run_seed as isize),
+        ];
+        for (name, value) in values.iter() {
+            let start = row;
+            line(r, name, color, &mut row);
+            r.plot_num(*value, col + 12, start, color);
+        }
+    }
+}
+
+// Without the feature every hook is a constant the compiler folds away.
+#[cfg(not(feature = "debug-tools"))]
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    pub(crate) fn is_god_mode(&self) -> bool {
+        false
+    }
+
+    pub(crate) fn debug_hold(&mut self) -> bool {
+        false
+    }
+
+    pub(crate) fn debug_frozen(&self) -> bool {
+        false
+    }
+
+    pub(crate) fn debug_key(&mut self, _key: DecodedKey) -> bool {
+        false
+    }
+}
+
+#[cfg(not(feature = "debug-tools"))]
+pub(crate) fn draw_overlay<R: Renderer>(_r: &mut R, _game: &MainGame) {}
+
+#[cfg(all(test, feature = "debug-tools"))]
+mod tests {
+    use super::*;
+    use pc_keyboard::KeyCode;
+
+    const SMALL: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         #   <    #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn plant_food_works_on_a_small_board() {
+        let mut game: SnakeGame<10,10> = SnakeGame::with_map(SMALL);
+        game.start();
+        let before = game.food_count();
+        assert!(!game.debug_key(DecodedKey::Unicode('n')));
+        assert!(game.debug_key(DecodedKey::RawKey(KeyCode::F12)));
+        assert!(game.debug_key(DecodedKey::Unicode('n')));
+        assert_eq!(game.food_count(), before + 1);
+    }
+}
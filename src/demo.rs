@@ -0,0 +1,49 @@
+use crate::{SnakeGame, Dir, Status};
+use crate::rival::manhattan;
+
+// How far ahead a demo move must stay clear before it is preferred.
+const DEMO_LOOKAHEAD: usize = 2;
+
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    pub fn is_demo(&self) -> bool {
+        self.demo
+    }
+
+    // Attract mode: a computer-driven game behind the title screen.
+    pub(crate) fn start_demo(&mut self) {
+        self.reset();
+        if self.status == Status::Playing {
+            self.demo = true;
+        } else {
+            self.status = Status::Menu;
+        }
+        self.idle_frames = 0;
+    }
+
+    pub(crate) fn end_demo(&mut self) {
+        self.demo = false;
+        self.reset();
+        if self.status == Status::Playing {
+            self.status = Status::Menu;
+        }
+        self.idle_frames = 0;
+    }
+
+    // Greedy: the legal move closest to the nearest food, preferring ones
+    // that stay clear for DEMO_LOOKAHEAD cells. Ties go to ALL_DIRS order.
+    pub(crate) fn demo_dir(&self) -> Option<Dir> {
+        let target = self.nearest_food(self.snake.pos);
+        let mut best: Option<(bool, usize, Dir)> = None;
+        for d in self.legal_moves().as_slice() {
+            let safe = self.is_dir_safe(*d, DEMO_LOOKAHEAD);
+            let distance = target.map_or(0, |food| manhattan(self.next_pos(self.snake.pos, *d), food));
+            let better = best.map_or(true, |(best_safe, closest, _)| {
+                (safe && !best_safe) || (safe == best_safe && distance < closest)
+            });
+            if better {
+                best = Some((safe, distance, *d));
+            }
+        }
+        best.map(|(_, _, d)| d)
+    }
+}
@@ -0,0 +1,114 @@
+use crate::Cell;
+
+pub(crate) const EFFECT_KINDS: usize = 4;
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum Effect {
+    Ghost,
+    SlowMotion,
+    Multiplier,
+    WallBreaker,
+}
+
+pub const ALL_EFFECTS: [Effect; EFFECT_KINDS] = [Effect::Ghost, Effect::SlowMotion, Effect::Multiplier, Effect::WallBreaker];
+
+impl Effect {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Effect::Ghost => "Ghost:",
+            Effect::SlowMotion => "Slow:",
+            Effect::Multiplier => "2x:",
+            Effect::WallBreaker => "Break:"
+        }
+    }
+
+    // How many moves one pickup lasts.
+    pub fn duration(&self) -> u32 {
+        match self {
+            Effect::Ghost => 30,
+            Effect::SlowMotion => 20,
+            Effect::Multiplier => 40,
+            Effect::WallBreaker => 15
+        }
+    }
+
+    // One in this many meals drops the pickup, if none is already out.
+    pub(crate) fn spawn_chance(&self) -> usize {
+        match self {
+            Effect::Ghost => 10,
+            Effect::SlowMotion => 8,
+            Effect::Multiplier => 12,
+            Effect::WallBreaker => 15
+        }
+    }
+
+    pub(crate) fn pickup(&self) -> Cell {
+        match self {
+            Effect::Ghost => Cell::Ghost,
+            Effect::SlowMotion => Cell::SlowMotion,
+            Effect::Multiplier => Cell::Multiplier,
+            Effect::WallBreaker => Cell::WallBreaker
+        }
+    }
+
+    pub(crate) fn from_pickup(cell: Cell) -> Option<Effect> {
+        ALL_EFFECTS.iter().copied().find(|effect| effect.pickup() == cell)
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Effect::Ghost => 0,
+            Effect::SlowMotion => 1,
+            Effect::Multiplier => 2,
+            Effect::WallBreaker => 3
+        }
+    }
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct Effects {
+    remaining: [u32; EFFECT_KINDS]
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        Effects {remaining: [0; EFFECT_KINDS]}
+    }
+
+    pub fn clear(&mut self) {
+        self.remaining = [0; EFFECT_KINDS];
+    }
+
+    // Picking up an effect that is already running restarts its timer.
+    pub fn grant(&mut self, effect: Effect) {
+        self.remaining[effect.index()] = effect.duration();
+    }
+
+    pub(crate) fn set_remaining(&mut self, effect: Effect, left: u32) {
+        self.remaining[effect.index()] = left;
+    }
+
+    pub fn remaining(&self, effect: Effect) -> u32 {
+        self.remaining[effect.index()]
+    }
+
+    pub fn is_active(&self, effect: Effect) -> bool {
+        self.remaining(effect) > 0
+    }
+
+    pub fn tick(&mut self) {
+        for left in self.remaining.iter_mut() {
+            *left = left.saturating_sub(1);
+        }
+    }
+
+    pub fn active(&self) -> impl Iterator<Item=(Effect, u32)> + '_ {
+        ALL_EFFECTS.iter().map(move |effect| (*effect, self.remaining(*effect))).filter(|(_, left)| *left > 0)
+    }
+}
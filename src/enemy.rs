@@ -0,0 +1,68 @@
+use crate::{SnakeGame, Position, Cell, OverCause, ALL_DIRS, MAX_ENEMIES};
+use crate::rival::manhattan;
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub(crate) struct Enemy<const WIDTH: usize, const HEIGHT: usize> {
+    pos: Position<WIDTH,HEIGHT>,
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    pub fn add_enemy(&mut self, p: Position<WIDTH,HEIGHT>) -> bool {
+        match self.enemies.iter().position(|enemy| enemy.is_none()) {
+            Some(slot) => {
+                self.enemies[slot] = Some(Enemy {pos: p});
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn enemy_at(&self, p: Position<WIDTH,HEIGHT>) -> bool {
+        self.enemies.iter().flatten().any(|enemy| enemy.pos == p)
+    }
+
+    pub fn enemy_positions(&self) -> impl Iterator<Item=Position<WIDTH,HEIGHT>> + '_ {
+        self.enemies.iter().flatten().map(|enemy| enemy.pos)
+    }
+
+    // Enemies only move every other update, which keeps them beatable.
+    // Frozen ones stand still but still catch a snake that runs into them.
+    pub(crate) fn update_enemies(&mut self) {
+        if !self.hazards_frozen {
+            self.enemy_turn = !self.enemy_turn;
+            if self.enemy_turn {
+                for i in 0..MAX_ENEMIES {
+                    if let Some(enemy) = self.enemies[i] {
+                        let pos = self.chase_step(enemy.pos);
+                        self.enemies[i] = Some(Enemy {pos});
+                    }
+                }
+            }
+        }
+        self.check_caught();
+    }
+
+    pub(crate) fn check_caught(&mut self) {
+        if self.enemy_at(self.snake.pos) && !self.is_god_mode() {
+            self.lose_round(OverCause::Caught);
+        }
+    }
+
+    // Greedy: the empty neighbor closest to the head, ties in ALL_DIRS
+    // order; an enemy with no better option stays put.
+    fn chase_step(&self, from: Position<WIDTH,HEIGHT>) -> Position<WIDTH,HEIGHT> {
+        let mut best = (manhattan(from, self.snake.pos), from);
+        for d in ALL_DIRS.iter() {
+            let n = from.neighbor(*d);
+            let open = n.is_legal() && !self.enemy_at(n)
+                && (n == self.snake.pos || self.cell(n) == Cell::Empty);
+            if open {
+                let distance = manhattan(n, self.snake.pos);
+                if distance < best.0 {
+                    best = (distance, n);
+                }
+            }
+        }
+        best.1
+    }
+}
@@ -0,0 +1,126 @@
+use spin::Mutex;
+use crate::cmos;
+
+pub const HIGH_SCORE_SLOTS: usize = 5;
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct HighScore {
+    pub score: u32,
+    pub level: usize,
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct HighScores {
+    entries: [Option<HighScore>; HIGH_SCORE_SLOTS]
+}
+
+// Lives outside the game so the table outlasts resets.
+static HIGH_SCORES: Mutex<HighScores> = Mutex::new(HighScores::new());
+
+impl Default for HighScores {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HighScores {
+    pub const fn new() -> Self {
+        HighScores {entries: [None; HIGH_SCORE_SLOTS]}
+    }
+
+    // Returns the rank the entry landed at. A tie goes below the scores
+    // already in the table, and a zero score never makes it in.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        if entry.score == 0 {
+            return None;
+        }
+        let rank = self.entries.iter().position(|slot| slot.map_or(true, |held| entry.score > held.score))?;
+        for i in (rank + 1..HIGH_SCORE_SLOTS).rev() {
+            self.entries[i] = self.entries[i - 1];
+        }
+        self.entries[rank] = Some(entry);
+        Some(rank)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item=HighScore> + '_ {
+        self.entries.iter().flatten().copied()
+    }
+
+    pub fn best(&self) -> Option<HighScore> {
+        self.entries[0]
+    }
+}
+
+// A new best is also written to CMOS NVRAM.
+pub fn record_high_score(entry: HighScore) -> Option<usize> {
+    let rank = HIGH_SCORES.lock().insert(entry);
+    if rank == Some(0) {
+        cmos::save_best(entry);
+    }
+    rank
+}
+
+// Call once at boot; the table stays empty if NVRAM holds no valid save.
+// A disk save restored first usually holds the same best already.
+pub fn restore_high_scores() {
+    if let Some(best) = cmos::load_best() {
+        let mut table = HIGH_SCORES.lock();
+        if table.best().map_or(true, |held| best.score > held.score) {
+            table.insert(best);
+        }
+    }
+}
+
+pub fn high_scores() -> HighScores {
+    *HIGH_SCORES.lock()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32) -> HighScore {
+        HighScore {score, level: 1}
+    }
+
+    fn scores(table: &HighScores) -> Vec<u32> {
+        table.entries().map(|entry| entry.score).collect()
+    }
+
+    #[test]
+    fn scores_are_kept_highest_first() {
+        let mut table = HighScores::new();
+        assert_eq!(table.insert(entry(50)), Some(0));
+        assert_eq!(table.insert(entry(80)), Some(0));
+        assert_eq!(table.insert(entry(60)), Some(1));
+        assert_eq!(scores(&table), [80, 60, 50]);
+        assert_eq!(table.best(), Some(entry(80)));
+    }
+
+    #[test]
+    fn a_tie_goes_below_the_score_already_held() {
+        let mut table = HighScores::new();
+        table.insert(HighScore {score: 70, level: 1});
+        assert_eq!(table.insert(HighScore {score: 70, level: 2}), Some(1));
+        assert_eq!(table.best(), Some(HighScore {score: 70, level: 1}));
+    }
+
+    #[test]
+    fn a_full_table_drops_its_lowest_score() {
+        let mut table = HighScores::new();
+        for score in 1..=HIGH_SCORE_SLOTS as u32 {
+            table.insert(entry(score * 10));
+        }
+        assert_eq!(table.insert(entry(5)), None);
+        assert_eq!(table.insert(entry(25)), Some(3));
+        assert_eq!(scores(&table), [50, 40, 30, 25, 20]);
+    }
+
+    #[test]
+    fn a_zero_score_never_gets_in() {
+        let mut table = HighScores::new();
+        assert_eq!(table.insert(entry(0)), None);
+        assert_eq!(table.best(), None);
+        assert_eq!(table.entries().count(), 0);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{Cell, Dir, Position, Snake, MAX_PORTALS, MAX_MOVERS, MAX_ENEMIES};
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum LevelError {
+    UnknownGlyph {row: usize, col: usize, glyph: char},
+    WrongWidth {row: usize, width: usize},
+    TooManyRows {row: usize},
+    TooFewRows {rows: usize},
+    DuplicateSnake {row: usize, col: usize},
+    MissingSnake,
+    ExtraPortal {row: usize, col: usize},
+    TooManyMovers {row: usize, col: usize},
+    TooManyEnemies {row: usize, col: usize},
+    UnpairedPortal {id: u8},
+}
+
+impl LevelError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            LevelError::UnknownGlyph {..} => "Unknown glyph",
+            LevelError::WrongWidth {..} => "Row has the wrong width",
+            LevelError::TooManyRows {..} => "Too many rows",
+            LevelError::TooFewRows {..} => "Too few rows",
+            LevelError::DuplicateSnake {..} => "More than one snake",
+            LevelError::MissingSnake => "No snake start",
+            LevelError::ExtraPortal {..} => "More than two portals share an id",
+            LevelError::TooManyMovers {..} => "Too many moving walls",
+            LevelError::TooManyEnemies {..} => "Too many enemies",
+            LevelError::UnpairedPortal {..} => "Portal has no partner"
+        }
+    }
+
+    pub fn row_col(&self) -> Option<(usize, usize)> {
+        match *self {
+            LevelError::UnknownGlyph {row, col, ..} | LevelError::DuplicateSnake {row, col}
+                | LevelError::ExtraPortal {row, col} | LevelError::TooManyMovers {row, col}
+                | LevelError::TooManyEnemies {row, col} => Some((row, col)),
+            LevelError::WrongWidth {row, width} => Some((row, width)),
+            LevelError::TooManyRows {row} => Some((row, 0)),
+            LevelError::TooFewRows {rows} => Some((rows, 0)),
+            LevelError::MissingSnake | LevelError::UnpairedPortal {..} => None
+        }
+    }
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct Level<const WIDTH: usize, const HEIGHT: usize> {
+    pub(crate) cells: [[Cell; WIDTH]; HEIGHT],
+    pub(crate) start: Snake<WIDTH,HEIGHT>,
+    pub(crate) name: Option<&'static str>,
+    pub(crate) par: Option<u32>,
+    pub(crate) time_limit: Option<u32>,
+    pub(crate) movers: [Option<Mover<WIDTH,HEIGHT>>; MAX_MOVERS],
+    pub(crate) enemies: [Option<Position<WIDTH,HEIGHT>>; MAX_ENEMIES],
+}
+
+// A run of moving wall; `lead` is the end facing `dir`.
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub(crate) struct Mover<const WIDTH: usize, const HEIGHT: usize> {
+    pub(crate) lead: Position<WIDTH,HEIGHT>,
+    pub(crate) len: usize,
+    pub(crate) dir: Dir,
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> Mover<WIDTH,HEIGHT> {
+    pub(crate) fn tail(&self) -> Position<WIDTH,HEIGHT> {
+        let mut p = self.lead;
+        for _ in 1..self.len {
+            p = p.neighbor(self.dir.opposite());
+        }
+        p
+    }
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> Level<WIDTH,HEIGHT> {
+    pub fn parse(text: &'static str) -> Result<Self, LevelError> {
+        let mut builder = LevelBuilder::new();
+        let mut row = 0;
+        for row_chars in text.split('\n') {
+            let row_chars = row_chars.trim();
+            if row_chars.is_empty() {
+                continue;
+            }
+            if let Some(comment) = row_chars.strip_prefix(';') {
+                builder.read_metadata(comment);
+                continue;
+            }
+            if row >= HEIGHT {
+                return Err(LevelError::TooManyRows {row});
+            }
+            let mut width = 0;
+            for (col, icon) in row_chars.chars().enumerate() {
+                if col >= WIDTH {
+                    return Err(LevelError::WrongWidth {row, width: row_chars.chars().count()});
+                }
+                builder.translate_icon(row, col, icon)?;
+                width += 1;
+            }
+            if width != WIDTH {
+                return Err(LevelError::WrongWidth {row, width});
+            }
+            row += 1;
+        }
+        if row < HEIGHT {
+            return Err(LevelError::TooFewRows {rows: row});
+        }
+        builder.finish()
+    }
+
+    pub fn from_chars(grid: &[[char; WIDTH]; HEIGHT]) -> Result<Self, LevelError> {
+        let mut builder = LevelBuilder::new();
+        for (row, row_chars) in grid.iter().enumerate() {
+            for (col, icon) in row_chars.iter().enumerate() {
+                builder.translate_icon(row, col, *icon)?;
+            }
+        }
+        builder.finish()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name
+    }
+
+    pub fn par(&self) -> Option<u32> {
+        self.par
+    }
+
+    pub fn time_limit(&self) -> Option<u32> {
+        self.time_limit
+    }
+}
+
+struct LevelBuilder<const WIDTH: usize, const HEIGHT: usize> {
+    cells: [[Cell; WIDTH]; HEIGHT],
+    start: Option<Snake<WIDTH,HEIGHT>>,
+    name: Option<&'static str>,
+    par: Option<u32>,
+    time_limit: Option<u32>,
+    seen_comment: bool,
+    portals: [u8; MAX_PORTALS],
+    movers: [Option<Mover<WIDTH,HEIGHT>>; MAX_MOVERS],
+    enemies: [Option<Position<WIDTH,HEIGHT>>; MAX_ENEMIES],
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> LevelBuilder<WIDTH,HEIGHT> {
+    fn new() -> Self {
+        LevelBuilder {cells: [[Cell::Empty; WIDTH]; HEIGHT], start: None, name: None, par: None, time_limit: None, seen_comment: false, portals: [0; MAX_PORTALS], movers: [None; MAX_MOVERS],
+            enemies: [None; MAX_ENEMIES]}
+    }
+
+    // Only the first comment line carries metadata: "; Name | par | seconds".
+    fn read_metadata(&mut self, comment: &'static str) {
+        if !self.seen_comment {
+            let mut parts = comment.splitn(3, '|');
+            self.name = parts.next().map(|name| name.trim()).filter(|name| !name.is_empty());
+            self.par = parts.next().and_then(|par| par.trim().parse().ok());
+            self.time_limit = parts.next().and_then(|seconds| seconds.trim().parse().ok());
+            self.seen_comment = true;
+        }
+    }
+
+    fn translate_icon(&mut self, row: usize, col: usize, icon: char) -> Result<(), LevelError> {
+        self.cells[row][col] = match icon {
+            '#' => Cell::Wall,
+            'X' => Cell::Bedrock,
+            '*' => Cell::Food,
+            '>' | '<' | '^' | 'v' => {
+                if self.start.is_some() {
+                    return Err(LevelError::DuplicateSnake {row, col});
+                }
+                self.start = Some(Snake::new(Position::new(row, col), icon));
+                Cell::Empty
+            },
+            ' ' => Cell::Empty,
+            'o' => Cell::Body,
+            '=' => self.add_mover(row, col, Dir::E)?,
+            '|' => self.add_mover(row, col, Dir::S)?,
+            'E' => {
+                let slot = self.enemies.iter().position(|enemy| enemy.is_none())
+                    .ok_or(LevelError::TooManyEnemies {row, col})?;
+                self.enemies[slot] = Some(Position::new(row, col));
+                Cell::Empty
+            },
+            'k' => Cell::Key,
+            'D' => Cell::Door,
+            '1'..='9' if icon as usize - '0' as usize <= MAX_PORTALS => {
+                let id = icon as u8 - b'0';
+                let seen = &mut self.portals[id as usize - 1];
+                if *seen == 2 {
+                    return Err(LevelError::ExtraPortal {row, col});
+                }
+                *seen += 1;
+                Cell::Portal(id)
+            },
+            _ => return Err(LevelError::UnknownGlyph {row, col, glyph: icon})
+        };
+        Ok(())
+    }
+
+    // '=' runs slide along their row and '|' runs along their column;
+    // a glyph that continues an existing run just lengthens it.
+    fn add_mover(&mut self, row: usize, col: usize, dir: Dir) -> Result<Cell, LevelError> {
+        let p = Position::new(row, col);
+        let run = self.movers.iter_mut().flatten()
+            .find(|mover| mover.dir == dir && mover.lead.neighbor(dir) == p);
+        match run {
+            Some(mover) => {
+                mover.lead = p;
+                mover.len += 1;
+            }
+            None => {
+                let slot = self.movers.iter().position(|mover| mover.is_none())
+                    .ok_or(LevelError::TooManyMovers {row, col})?;
+                self.movers[slot] = Some(Mover {lead: p, len: 1, dir});
+            }
+        }
+        Ok(Cell::MovingWall)
+    }
+
+    fn finish(self) -> Result<Level<WIDTH,HEIGHT>, LevelError> {
+        if let Some(i) = self.portals.iter().position(|seen| *seen == 1) {
+            return Err(LevelError::UnpairedPortal {id: i as u8 + 1});
+        }
+        let start = self.start.ok_or(LevelError::MissingSnake)?;
+        Ok(Level {cells: self.cells, start, name: self.name, par: self.par, time_limit: self.time_limit, movers: self.movers, enemies: self.enemies})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMED: &str =
+        "; The Box | 12 | 90
+         ; Only the first comment counts | 99
+         ##########
+         #        #
+         #        #
+         #        #
+         #   <    #
+         #        #
+         #        #
+         #        #
+         #       *#
+         ##########";
+
+    #[test]
+    fn first_comment_names_the_map() {
+        let level: Level<10,10> = Level::parse(NAMED).unwrap();
+        assert_eq!(level.name(), Some("The Box"));
+        assert_eq!(level.par(), Some(12));
+        assert_eq!(level.time_limit(), Some(90));
+    }
+
+    #[test]
+    fn named_map_reaches_the_game() {
+        let game: crate::SnakeGame<10,10> = crate::SnakeGame::with_map(NAMED);
+        assert_eq!(game.map_name(), Some("The Box"));
+        assert_eq!(game.map_par(), Some(12));
+    }
+}
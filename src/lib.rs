@@ -3,97 +3,584 @@
 #![cfg_attr(not(test), no_std)]
 
 use core::ops::Sub;
+use spin::Mutex;
 use pc_keyboard::{DecodedKey, KeyCode};
 
-use pluggable_interrupt_os::vga_buffer::{BUFFER_WIDTH, BUFFER_HEIGHT, plot, plot_str, plot_num, clear_row, ColorCode, Color};
+use pluggable_interrupt_os::vga_buffer::{BUFFER_WIDTH, BUFFER_HEIGHT, Color};
 
+mod prng;
+mod snapshot;
+mod level;
+mod effects;
+mod rival;
+mod enemy;
+mod maze;
+mod campaign;
+mod high_scores;
+mod cmos;
+mod ata;
+mod save;
+mod achievements;
+mod demo;
+mod replay;
+mod cheat_codes;
+mod debug;
+mod render;
 
+pub use prng::Rng;
+pub use snapshot::DecodeError;
+pub use level::{Level, LevelError};
+pub use effects::{Effect, Effects};
+pub use rival::Winner;
+pub use campaign::{Campaign, Stage};
+pub use high_scores::{HighScore, HighScores, high_scores, restore_high_scores, HIGH_SCORE_SLOTS};
+pub use ata::{AtaDrive, AtaError, SECTOR_SIZE};
+pub use save::{SaveData, SaveError, load_save, write_save};
+pub use achievements::{Achievement, Achievements, ALL_ACHIEVEMENTS};
+pub use cheat_codes::{CheatCode, CheatEffect, DEFAULT_CHEAT_CODES};
+pub use render::{Renderer, Vga, ColorCode};
 
-const GAME_HEIGHT: usize = BUFFER_HEIGHT - 2;
-const HEADER_SPACE: usize = BUFFER_HEIGHT - GAME_HEIGHT;
+
+
+const SCORE_ROW: usize = 0;
+const SUBHEADER_ROW: usize = 1;
+const HEADER_SPACE: usize = header_height();
+const GAME_HEIGHT: usize = BUFFER_HEIGHT - HEADER_SPACE;
+const LIVES_COL: usize = 12;
+const LEVEL_COL: usize = 22;
+const SPEED_COL: usize = 32;
+const MAP_NAME_COL: usize = 42;
+const KEYS_COL: usize = 32;
+const HUNGER_COL: usize = 40;
+const HUNGER_BAR: usize = 9;
+const EFFECTS_COL: usize = 50;
+const EFFECT_WIDTH: usize = 10;
+const COMBO_COL: usize = 10;
+const TIME_COL: usize = 20;
 
 pub type MainGame = SnakeGame<BUFFER_WIDTH,GAME_HEIGHT>;
 
+const fn header_height() -> usize {
+    SUBHEADER_ROW + 1
+}
+
 pub fn tick(game: &mut MainGame) {
-    if game.countdown_complete() {
-        game.update();
-        draw(game);
+    tick_with_hud(game, &mut |_| {});
+}
+
+static VGA: Mutex<Vga> = Mutex::new(Vga::new());
+
+pub fn tick_with_hud(game: &mut MainGame, hud: &mut dyn FnMut(&MainGame)) {
+    hud_tick(game, hud, |game| {
+        let mut vga = VGA.lock();
+        let drew = tick_with_renderer(&mut *vga, game);
+        vga.flush();
+        drew
+    });
+}
+
+// The screen is let go of before the HUD runs, so the HUD can draw
+// straight to VGA memory and call redraw_whole_board.
+fn hud_tick(game: &mut MainGame, hud: &mut dyn FnMut(&MainGame), render: impl FnOnce(&mut MainGame) -> bool) {
+    if render(game) {
+        hud(game);
+    }
+}
+
+// True when the board was drawn this tick, which is when a HUD should
+// draw over it.
+pub fn tick_with_renderer<R: Renderer>(r: &mut R, game: &mut MainGame) -> bool {
+    game.advance_frame();
+    match game.status() {
+        Status::Menu => draw_title_screen(r, game),
+        Status::Paused => draw_header(r, game),
+        _ => {
+            if game.countdown_complete() {
+                game.update();
+                draw(r, game);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn draw_title_screen<R: Renderer>(r: &mut R, game: &MainGame) {
+    let title_color = ColorCode::new(Color::LightGreen, Color::Black);
+    let text_color = ColorCode::new(Color::White, Color::Black);
+    for row in 0..BUFFER_HEIGHT {
+        r.clear_row(row, Color::Black);
+    }
+    let lines: [(&str, ColorCode); 10] = [
+        ("BAREMETAL SNAKE", title_color),
+        ("", text_color),
+        ("Arrows / WASD: steer", text_color),
+        ("Q / E: turn left / right", text_color),
+        ("P: pause", text_color),
+        ("1 / 2 / 3: Easy / Normal / Hard", text_color),
+        ("M: toggle endless mode", text_color),
+        ("[ / ]: choose stage", text_color),
+        ("G: type a seed", text_color),
+        ("Press Enter to start", title_color),
+    ];
+    let top = (BUFFER_HEIGHT - lines.len()) / 2;
+    for (i, (text, color)) in lines.iter().enumerate() {
+        r.plot_str(text, (BUFFER_WIDTH - text.len()) / 2, top + i, *color);
+    }
+    let difficulty_text = "Difficulty:";
+    let name = game.difficulty().name();
+    let col = (BUFFER_WIDTH - difficulty_text.len() - 1 - name.len()) / 2;
+    r.plot_str(difficulty_text, col, top + lines.len() + 1, text_color);
+    r.plot_str(name, col + difficulty_text.len() + 1, top + lines.len() + 1, title_color);
+    draw_stage_select(r, game, top + lines.len() + 2);
+    if game.config().endless {
+        let endless_text = "Endless mode";
+        r.plot_str(endless_text, (BUFFER_WIDTH - endless_text.len()) / 2, top + lines.len() + 3, title_color);
+    }
+    draw_seed_select(r, game, top + lines.len() + 4);
+    if let Some(toast) = game.toast() {
+        r.plot_str(toast, (BUFFER_WIDTH - toast.len()) / 2, BUFFER_HEIGHT - 1, ColorCode::new(Color::Yellow, Color::Black));
+    }
+}
+
+fn draw_seed_select<R: Renderer>(r: &mut R, game: &MainGame, row: usize) {
+    let seed_text = "Seed:";
+    let col = (BUFFER_WIDTH - seed_text.len() - 11) / 2;
+    r.plot_str(seed_text, col, row, ColorCode::new(Color::White, Color::Black));
+    let value_col = col + seed_text.len() + 1;
+    if let Some(typed) = game.seed_entry() {
+        let color = ColorCode::new(Color::Black, Color::LightGreen);
+        let mut end = value_col;
+        if typed != 0 {
+            r.plot_num(typed as isize, value_col, row, color);
+            let mut rest = typed;
+            while rest > 0 {
+                rest /= 10;
+                end += 1;
+            }
+        }
+        r.plot('_', end, row, color);
+    } else if let Some(seed) = game.config().seed {
+        r.plot_num(seed as isize, value_col, row, ColorCode::new(Color::LightGreen, Color::Black));
+    } else {
+        r.plot_str("random", value_col, row, ColorCode::new(Color::DarkGray, Color::Black));
+    }
+}
+
+// Locked stages are greyed out and the selected one is highlighted.
+fn draw_stage_select<R: Renderer>(r: &mut R, game: &MainGame, row: usize) {
+    let campaign = game.campaign();
+    if campaign.is_empty() {
+        return;
+    }
+    let stage_text = "Stage:";
+    let col = (BUFFER_WIDTH - stage_text.len() - 3 * campaign.len()) / 2;
+    r.plot_str(stage_text, col, row, ColorCode::new(Color::White, Color::Black));
+    for i in 0..campaign.len() {
+        let color = if i == campaign.selected() {
+            ColorCode::new(Color::Black, Color::LightGreen)
+        } else if campaign.is_unlocked(i) {
+            ColorCode::new(Color::White, Color::Black)
+        } else {
+            ColorCode::new(Color::DarkGray, Color::Black)
+        };
+        r.plot_num(i as isize + 1, col + stage_text.len() + 2 + 3 * i, row, color);
+    }
+}
+
+fn draw<R: Renderer>(r: &mut R, game: &MainGame) {
+    draw_header(r, game);
+    draw_board(r, game);
+    if game.is_finished() {
+        draw_high_scores(r, game);
     }
+    debug::draw_overlay(r, game);
 }
 
-fn draw(game: &MainGame) {
-    draw_header(game);
-    draw_board(game);
+// Drawn over the middle of the board; this run's entry is highlighted.
+fn draw_high_scores<R: Renderer>(r: &mut R, game: &MainGame) {
+    let width = 20;
+    let col = (BUFFER_WIDTH - width) / 2;
+    let height = HIGH_SCORE_SLOTS + 4;
+    let top = HEADER_SPACE + (GAME_HEIGHT - height) / 2;
+    let box_color = ColorCode::new(Color::White, Color::Blue);
+    for row in top..top + height {
+        for i in 0..width {
+            r.plot(' ', col + i, row, box_color);
+        }
+    }
+    let title = "HIGH SCORES";
+    r.plot_str(title, col + (width - title.len()) / 2, top, ColorCode::new(Color::Yellow, Color::Blue));
+    for (rank, entry) in high_scores().entries().enumerate() {
+        let row = top + 2 + rank;
+        let color = if game.score_rank() == Some(rank) {
+            ColorCode::new(Color::Yellow, Color::Blue)
+        } else {
+            box_color
+        };
+        r.plot_num(rank as isize + 1, col + 2, row, color);
+        r.plot('.', col + 3, row, color);
+        r.plot_num(entry.score as isize, col + 5, row, color);
+        r.plot_str("Lv", col + 13, row, color);
+        r.plot_num(entry.level as isize, col + 16, row, color);
+    }
+    r.plot_str("Seed:", col + 2, top + height - 2, box_color);
+    r.plot_num(game.run_seed() as isize, col + 8, top + height - 2, box_color);
+    if game.replay_len() > 0 && !game.two_player() {
+        r.plot_str("R: watch replay", col + 2, top + height - 1, box_color);
+    }
 }
 
-fn draw_header(game: &MainGame) {
+fn draw_header<R: Renderer>(r: &mut R, game: &MainGame) {
     match game.status() {
-        Status::Normal => draw_normal_header(game),
-        Status::Over => draw_game_over_header(game)
+        Status::Playing => {
+            draw_normal_header(r, game);
+            if game.is_demo() {
+                draw_subheader(r, "DEMO. Press any key.");
+            } else if game.is_playback() {
+                draw_subheader(r, "REPLAY. Press any key to stop.");
+            } else if let Some(toast) = game.toast() {
+                draw_subheader(r, toast);
+            }
+        }
+        Status::Paused => {
+            draw_normal_header(r, game);
+            draw_subheader(r, "PAUSED. Press P to resume.");
+        }
+        Status::GameOver | Status::Victory => draw_game_over_header(r, game),
+        Status::Menu => {}
+        Status::Error => draw_error_header(r, game)
     }
 }
 
-fn draw_normal_header(game: &MainGame) {
-    clear_row(1, Color::Black);
+fn draw_normal_header<R: Renderer>(r: &mut R, game: &MainGame) {
     let header_color = ColorCode::new(Color::White, Color::Black);
-    let score_text = "Score:";
-    clear_row(0, Color::Black);
-    clear_row(1, Color::Black);
-    plot_str(score_text, 0, 0, header_color);
-    plot_num(game.score() as isize, score_text.len() + 1, 0, header_color);
+    let score_text = if game.two_player() {"P1:"} else {"Score:"};
+    for row in 0..HEADER_SPACE {
+        r.clear_row(row, Color::Black);
+    }
+    r.plot_str(score_text, 0, SCORE_ROW, header_color);
+    r.plot_num(game.score() as isize, score_text.len() + 1, SCORE_ROW, header_color);
+    if let Some(score) = game.player_two_score() {
+        let p2_text = "P2:";
+        r.plot_str(p2_text, 0, SUBHEADER_ROW, header_color);
+        r.plot_num(score as isize, p2_text.len() + 1, SUBHEADER_ROW, header_color);
+    }
+    let mut hearts = [' '; MAX_LIVES];
+    let shown = game.hearts(&mut hearts);
+    for (i, heart) in hearts[..shown].iter().enumerate() {
+        r.plot(*heart, LIVES_COL + i, SCORE_ROW, ColorCode::new(Color::LightRed, Color::Black));
+    }
+    if game.level_count() > 0 {
+        let level_text = "Level:";
+        r.plot_str(level_text, LEVEL_COL, SCORE_ROW, header_color);
+        r.plot_num(game.current_level() as isize + 1, LEVEL_COL + level_text.len() + 1, SCORE_ROW, header_color);
+    }
+    let speed_text = "Speed:";
+    r.plot_str(speed_text, SPEED_COL, SCORE_ROW, header_color);
+    r.plot_num(game.speed_level() as isize, SPEED_COL + speed_text.len() + 1, SCORE_ROW, header_color);
+    if let Some(name) = game.map_name() {
+        r.plot_str(name, MAP_NAME_COL, SCORE_ROW, header_color);
+    }
+    if game.combo() > 0 {
+        let combo_text = "Combo:";
+        r.plot_str(combo_text, COMBO_COL, SUBHEADER_ROW, header_color);
+        r.plot_num(game.combo() as isize, COMBO_COL + combo_text.len(), SUBHEADER_ROW, header_color);
+    }
+    let time_left = match (game.time_left(), game.level_time_remaining()) {
+        (Some(run), Some(level)) => Some(run.min(level)),
+        (run, level) => run.or(level)
+    };
+    if let Some(left) = time_left {
+        let time_text = "Time:";
+        let seconds = (left * 60 + TICKS_PER_MINUTE - 1) / TICKS_PER_MINUTE;
+        r.plot_str(time_text, TIME_COL, SUBHEADER_ROW, header_color);
+        r.plot_num(seconds as isize, TIME_COL + time_text.len() + 1, SUBHEADER_ROW, header_color);
+    }
+    if game.keys_held() > 0 {
+        let keys_text = "Keys:";
+        r.plot_str(keys_text, KEYS_COL, SUBHEADER_ROW, header_color);
+        r.plot_num(game.keys_held() as isize, KEYS_COL + keys_text.len(), SUBHEADER_ROW, header_color);
+    }
+    if let Some(total) = game.config().hunger {
+        let filled = (game.hunger_left() as usize * HUNGER_BAR + total as usize - 1) / total as usize;
+        let bar_color = ColorCode::new(Color::Yellow, Color::Black);
+        for i in 0..HUNGER_BAR {
+            r.plot(if i < filled {'='} else {' '}, HUNGER_COL + i, SUBHEADER_ROW, bar_color);
+        }
+    }
+    // Only as many as fit on the line; the rest still run, just unlisted.
+    let shown = (BUFFER_WIDTH - EFFECTS_COL) / EFFECT_WIDTH;
+    for (i, (effect, left)) in game.effects().active().take(shown).enumerate() {
+        let col = EFFECTS_COL + i * EFFECT_WIDTH;
+        r.plot_str(effect.label(), col, SUBHEADER_ROW, header_color);
+        r.plot_num(left as isize, col + effect.label().len() + 1, SUBHEADER_ROW, header_color);
+    }
+    if let Some(par) = game.map_par() {
+        let par_text = "Par:";
+        let par_col = BUFFER_WIDTH - par_text.len() - 6;
+        r.plot_str(par_text, par_col, SCORE_ROW, header_color);
+        r.plot_num(par as isize, par_col + par_text.len() + 1, SCORE_ROW, header_color);
+    }
 }
 
-fn draw_subheader(subheader: &str) {
-    plot_str(subheader, 0, 1, ColorCode::new(Color::LightRed, Color::Black));
+fn draw_subheader<R: Renderer>(r: &mut R, subheader: &str) {
+    r.plot_str(subheader, 0, SUBHEADER_ROW, ColorCode::new(Color::LightRed, Color::Black));
 }
 
-fn draw_game_over_header(game: &MainGame) {
-    draw_normal_header(game);
-    if game.food_eaten >= 30{
-        draw_subheader("Game Won. Press S to restart.");
+fn draw_game_over_header<R: Renderer>(r: &mut R, game: &MainGame) {
+    draw_normal_header(r, game);
+    if let Some(winner) = game.winner() {
+        draw_subheader(r, match winner {
+            Winner::PlayerOne => "Player 1 wins! Press S to restart.",
+            Winner::PlayerTwo => "Player 2 wins! Press S to restart.",
+            Winner::Draw => "Draw! Press S to restart."
+        });
+    } else if game.status() == Status::Victory {
+        draw_subheader(r, "Game Won. Press S to restart.");
+    } else if game.over_cause() == OverCause::Starved {
+        draw_subheader(r, "Starved! Press S to restart.");
+    } else if game.over_cause() == OverCause::Caught {
+        draw_subheader(r, "Caught! Press S to restart.");
+    } else if game.over_cause() == OverCause::TimeUp {
+        let message = "Time up! Press S to restart. Final score:";
+        draw_subheader(r, message);
+        r.plot_num(game.score() as isize, message.len() + 1, SUBHEADER_ROW, ColorCode::new(Color::LightRed, Color::Black));
+    } else if !game.has_win_target() {
+        let message = "Game over. Press S to restart. Final score:";
+        draw_subheader(r, message);
+        r.plot_num(game.score() as isize, message.len() + 1, SUBHEADER_ROW, ColorCode::new(Color::LightRed, Color::Black));
     } else {
-        draw_subheader("Game over. Press S to restart.");
+        let message = "Game over. Press S to restart. Food left to win:";
+        draw_subheader(r, message);
+        let left = game.config().win_threshold.saturating_sub(game.food_eaten);
+        r.plot_num(left as isize, message.len() + 1, SUBHEADER_ROW, ColorCode::new(Color::LightRed, Color::Black));
+    }
+
+}
+
+fn draw_error_header<R: Renderer>(r: &mut R, game: &MainGame) {
+    let error_color = ColorCode::new(Color::LightRed, Color::Black);
+    for row in 0..HEADER_SPACE {
+        r.clear_row(row, Color::Black);
+    }
+    if let Some(error) = game.level_error() {
+        r.plot_str("Level error:", 0, SCORE_ROW, error_color);
+        r.plot_str(error.message(), 13, SCORE_ROW, error_color);
+        if let Some((row, col)) = error.row_col() {
+            r.plot_str("Row:", 0, SUBHEADER_ROW, error_color);
+            r.plot_num(row as isize, 5, SUBHEADER_ROW, error_color);
+            r.plot_str("Col:", 10, SUBHEADER_ROW, error_color);
+            r.plot_num(col as isize, 15, SUBHEADER_ROW, error_color);
+        }
     }
+}
 
+fn draw_board<R: Renderer>(r: &mut R, game: &MainGame) {
+    draw_board_at(r, game, HEADER_SPACE);
 }
 
-fn draw_board(game: &MainGame) {
+// `top` is the first screen row below the header.
+fn draw_board_at<R: Renderer>(r: &mut R, game: &MainGame, top: usize) {
     for p in game.cell_pos_iter() {
-        let (row, col) = p.row_col();
-        let (c, color) = get_icon_color(game, p, &game.cell(p));
-        plot(c, col, row + HEADER_SPACE, color);
+        let (row, col) = game.rotation().apply_row_col(p.row_col(), BUFFER_WIDTH, GAME_HEIGHT);
+        let (c, color) = if game.is_visible(p) {
+            get_icon_color(game, p, &game.cell(p))
+        } else {
+            (' ', ColorCode::new(Color::Black, Color::Black))
+        };
+        r.plot(c, col, row + top, color);
     }
 }
 
+// For HUDs, which draw straight to VGA memory after each frame.
+pub fn redraw_whole_board() {
+    VGA.lock().invalidate();
+}
+
 fn get_icon_color(game: &MainGame, p: Position<BUFFER_WIDTH,GAME_HEIGHT>, cell: &Cell) -> (char, ColorCode) {
     let (icon, foreground) =
-        if p == game.snake_at() {
+        if game.enemy_at(p) {
+            ('&', Color::LightMagenta)
+        } else if Some(p) == game.rival_head() {
+            (game.rival_icon().unwrap_or('@'), RIVAL_COLOR)
+        } else if p == game.snake_at() {
             (match game.status() {
-                Status::Over => '*',
+                Status::GameOver => '*',
                 _ => game.snake_icon()
-            }, Color::Green)
+            }, game.effective_head_color())
+        } else if let Some(c) = game.popup_at(p) {
+            (c, Color::Yellow)
         } else {
             match cell {
                 Cell::Food => ('.', Color::White),
-                Cell::Empty => (' ', Color::Black),
+                Cell::BonusFood if game.bonus_blink_off() => (' ', game.board_color()),
+                Cell::BonusFood => ('$', Color::Yellow),
+                Cell::Poison => ('x', Color::Red),
+                Cell::Empty => match game.background(p) {
+                    Some(tile) => (tile, Color::DarkGray),
+                    None => (' ', game.board_color())
+                },
                 Cell::Wall => ('#', Color::Blue),
-                Cell::Body => ('o', Color::Green)
+                Cell::Bedrock => ('#', Color::LightGray),
+                Cell::Body => ('o', game.body_color_at(p)),
+                Cell::Ghost => ('G', GHOST_COLOR),
+                Cell::SlowMotion => ('~', Color::LightMagenta),
+                Cell::Multiplier => ('2', Color::Yellow),
+                Cell::WallBreaker => ('!', Color::LightRed),
+                Cell::Shrink => ('-', Color::LightBlue),
+                Cell::RivalBody => ('o', RIVAL_COLOR),
+                Cell::Portal(id) => ((b'0' + id) as char, Color::Cyan),
+                Cell::Key => ('k', Color::Yellow),
+                Cell::Door => ('D', Color::Brown),
+                Cell::MovingWall => ('#', Color::LightBlue)
             }
 
         };
-    (icon, ColorCode::new(foreground, Color::Black))
+    (icon, ColorCode::new(foreground, game.board_color()))
 }
 
+const SNAKE_COLOR: Color = Color::Green;
+const RIVAL_COLOR: Color = Color::LightRed;
+
 const UPDATE_FREQUENCY: usize = 3;
+const RAMP_STEP: u32 = 5;
+const RAMP_LINEAR_FOOD: u32 = 10;
+const PEDAL_SLOWDOWN: usize = 2;
+const MAX_BODY: usize = 1024;
+const INPUT_QUEUE_LEN: usize = 3;
+const DEFAULT_LIVES: u32 = 3;
+const DEFAULT_WIN_THRESHOLD: u32 = 30;
+const DEFAULT_FOOD_TARGET: usize = 1;
+const MAX_LIVES: usize = 9;
+const INVULNERABLE_MOVES: u32 = 10;
+const HARD_SPAWN_DELAY: u32 = 8;
+const BONUS_POINTS: u32 = 5;
+const BONUS_CHANCE: usize = 5;
+const BONUS_LIFETIME: u32 = 40;
+const BONUS_BLINK: u32 = 12;
+const POISON_PENALTY: u32 = 3;
+const MAX_POISON: usize = 3;
+const SLOW_MOTION_FACTOR: usize = 2;
+const SCORE_MULTIPLIER: u32 = 2;
+const SHRINK_SEGMENTS: usize = 3;
+const SHRINK_CHANCE: usize = 6;
+const MAX_WANDERERS: usize = 8;
+const MAX_PORTALS: usize = 4;
+const MAX_MOVERS: usize = 8;
+const MOVER_PERIOD: u32 = 3;
+const MAX_ENEMIES: usize = 4;
+const WANDER_COOLDOWN: u32 = 4;
+// The shrinking arena stops once its inside is this many cells from the middle.
+const MIN_ARENA_RADIUS: usize = 3;
+const OBSTACLE_TRIES: usize = 8;
+const ENDLESS_OBSTACLE_EVERY: u32 = 10;
+// Food eaten within this many ticks of the last meal extends the combo.
+const COMBO_WINDOW: u64 = 55;
+const COMBO_POINTS: u32 = 1;
+const MAX_POPUPS: usize = 4;
+const POPUP_FRAMES: u64 = 12;
+const ACHIEVEMENT_TOAST_FRAMES: u64 = 60;
+const STRAIGHT_SHOOTER_FOOD: u32 = 10;
+const COMBO_ACHIEVEMENT: u32 = 5;
+const MARATHON_FOOD: u32 = 50;
+const ATTRACT_IDLE_SECONDS: u32 = 30;
+// How long a finished demo game stays up before going back to the menu.
+const DEMO_OVER_FRAMES: u64 = 40;
+const GHOST_COLOR: Color = Color::LightCyan;
+const DEFAULT_BOARD_COLOR: Color = Color::Black;
+// CP437 code point 3 is a solid heart.
+const HEART: char = '\u{3}';
+const EMPTY_HEART: char = '.';
 
 #[derive(Copy,Debug,Clone,Eq,PartialEq)]
 pub struct SnakeGame<const WIDTH: usize, const HEIGHT: usize> {
     cells: [[Cell; WIDTH]; HEIGHT],
+    background: [[char; WIDTH]; HEIGHT],
+    board_color: Color,
+    map: MapSource<WIDTH,HEIGHT>,
+    config: GameConfig,
+    campaign: Campaign,
+    current_level: usize,
+    level_food: u32,
+    level_time_limit: Option<u32>,
+    level_started: u64,
+    score_rank: Option<usize>,
+    save_pending: bool,
+    combo: u32,
+    last_meal: u64,
+    popups: [Option<Popup<WIDTH,HEIGHT>>; MAX_POPUPS],
+    achievements: Achievements,
+    straight_food: u32,
+    demo: bool,
+    idle_frames: u64,
+    replay: replay::Replay,
+    playback: Option<usize>,
+    run_seed: u32,
+    seed_entry: Option<u32>,
+    map_name: Option<&'static str>,
+    map_par: Option<u32>,
+    level_error: Option<LevelError>,
     snake: Snake<WIDTH,HEIGHT>,
+    start: Snake<WIDTH,HEIGHT>,
+    body: Body<WIDTH,HEIGHT>,
+    pending_growth: u32,
+    food_at_tail: bool,
     status: Status,
     food_eaten: u32,
+    points: u32,
+    breakdown: ScoreBreakdown,
+    zones: [Option<Zone<WIDTH,HEIGHT>>; MAX_ZONES],
     countdown: usize,
-    last_key: Option<Dir>
+    difficulty: Difficulty,
+    spawn_delay: u32,
+    food_on_board: usize,
+    bonus: Option<(Position<WIDTH,HEIGHT>, u32)>,
+    effects: Effects,
+    hunger_left: u32,
+    wandering_food: bool,
+    wanderers: [Option<Wanderer<WIDTH,HEIGHT>>; MAX_WANDERERS],
+    hazards_frozen: bool,
+    rival_enabled: bool,
+    rival: Option<rival::Rival<WIDTH,HEIGHT>>,
+    two_player: bool,
+    winner: Option<Winner>,
+    keys_held: u32,
+    movers: [Option<level::Mover<WIDTH,HEIGHT>>; MAX_MOVERS],
+    mover_countdown: u32,
+    enemies: [Option<enemy::Enemy<WIDTH,HEIGHT>>; MAX_ENEMIES],
+    enemy_turn: bool,
+    arena_inset: usize,
+    next_shrink: u64,
+    random_turn_on_eat: bool,
+    over_cause: OverCause,
+    lives: u32,
+    invulnerable: u32,
+    inputs: InputQueue,
+    rng: Rng,
+    magnetic_walls: bool,
+    ramp: RampKind,
+    pedal_mode: bool,
+    pedal_held: bool,
+    rotation: Rotation,
+    moves: u32,
+    turns: u32,
+    grades: &'static [GradeThreshold],
+    cheat_codes: &'static [CheatCode],
+    code_matcher: cheat_codes::SequenceMatcher,
+    rainbow: bool,
+    #[cfg(feature = "debug-tools")]
+    debug: debug::DebugOverlay,
+    eaten: [Position<WIDTH,HEIGHT>; EATEN_HISTORY],
+    eaten_count: usize,
+    frames: u64,
+    elapsed_ticks: u64,
+    paused_ticks: u64,
+    auto_restart: Option<u64>,
+    over_frames: u64,
+    toast: [u8; TOAST_LEN],
+    toast_len: usize,
+    toast_until: u64
 }
 
 #[derive(Debug,Copy,Clone,Eq,PartialEq)]
@@ -130,6 +617,69 @@ impl Dir {
             Dir::W => Dir::N
         }
     }
+
+    pub fn opposite(&self) -> Dir {
+        match self {
+            Dir::N => Dir::S,
+            Dir::S => Dir::N,
+            Dir::E => Dir::W,
+            Dir::W => Dir::E
+        }
+    }
+}
+
+#[derive(Debug,Copy,Clone,Eq,PartialEq)]
+pub enum Rotation {
+    Normal,
+    Half,
+}
+
+impl Rotation {
+    pub fn apply(&self, d: Dir) -> Dir {
+        match self {
+            Rotation::Normal => d,
+            Rotation::Half => d.opposite()
+        }
+    }
+
+    pub fn apply_row_col(&self, (row, col): (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Rotation::Normal => (row, col),
+            Rotation::Half => (height - 1 - row, width - 1 - col)
+        }
+    }
+}
+
+#[derive(Debug,Copy,Clone,Eq,PartialEq)]
+pub struct DirList {
+    dirs: [Dir; 4], len: usize
+}
+
+impl DirList {
+    fn new() -> Self {
+        DirList {dirs: [Dir::N; 4], len: 0}
+    }
+
+    fn push(&mut self, d: Dir) {
+        self.dirs[self.len] = d;
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, d: Dir) -> bool {
+        self.as_slice().contains(&d)
+    }
+
+    pub fn as_slice(&self) -> &[Dir] {
+        &self.dirs[..self.len]
+    }
 }
 
 impl From<char> for Dir {
@@ -151,6 +701,28 @@ pub enum Cell {
     Empty,
     Wall,
     Body,
+    Bedrock,
+    BonusFood,
+    Poison,
+    Ghost,
+    SlowMotion,
+    Multiplier,
+    WallBreaker,
+    Shrink,
+    RivalBody,
+    Portal(u8),
+    Key,
+    Door,
+    MovingWall,
+}
+
+impl Cell {
+    pub fn is_wall(&self) -> bool {
+        match self {
+            Cell::Wall | Cell::Bedrock | Cell::Door | Cell::MovingWall => true,
+            _ => false
+        }
+    }
 }
 
 #[derive(Debug,Copy,Clone,Eq,PartialEq)]
@@ -167,6 +739,10 @@ impl <const WIDTH: usize, const HEIGHT: usize> Sub for Position<WIDTH,HEIGHT> {
 }
 
 impl <const WIDTH: usize, const HEIGHT: usize> Position<WIDTH,HEIGHT> {
+    pub fn new(row: usize, col: usize) -> Self {
+        Position {row: row as i16, col: col as i16}
+    }
+
     pub fn is_legal(&self) -> bool {
         0 <= self.col && self.col < WIDTH as i16 && 0 <= self.row && self.row < HEIGHT as i16
     }
@@ -175,6 +751,11 @@ impl <const WIDTH: usize, const HEIGHT: usize> Position<WIDTH,HEIGHT> {
         (self.row as usize, self.col as usize)
     }
 
+    pub fn wrapped_neighbor(&self, d: Dir) -> Position<WIDTH,HEIGHT> {
+        let n = self.neighbor(d);
+        Position {row: n.row.rem_euclid(HEIGHT as i16), col: n.col.rem_euclid(WIDTH as i16)}
+    }
+
     pub fn neighbor(&self, d: Dir) -> Position<WIDTH,HEIGHT> {
         match d {
             Dir::N => Position {row: self.row - 1, col: self.col},
@@ -211,210 +792,2313 @@ impl <const WIDTH: usize, const HEIGHT: usize> Snake<WIDTH,HEIGHT> {
     }
 }
 
+#[derive(Copy,Clone,Eq,PartialEq,Debug,Default)]
+pub struct ScoreBreakdown {
+    pub base: u32,
+    pub zone_bonus: u32,
+    pub multiplier_bonus: u32,
+    pub combo_bonus: u32,
+}
+
 #[derive(Copy,Clone,Eq,PartialEq,Debug)]
-pub enum Status {
-    Normal,
-    Over,
+pub struct GradeThreshold {
+    pub grade: char,
+    pub min_score: u32,
+    pub min_food_per_100_moves: u32,
+    pub max_turns_per_food: u32,
+    pub max_ticks_per_food: u64,
 }
 
-const SNAKE_START_DIR: [Dir; 4] = [Dir::E, Dir::W, Dir::E, Dir::W];
+// A run earns the first grade whose every threshold it meets, or 'D'.
+pub const DEFAULT_GRADES: [GradeThreshold; 4] = [
+    GradeThreshold {grade: 'S', min_score: 30, min_food_per_100_moves: 5, max_turns_per_food: 2, max_ticks_per_food: 150},
+    GradeThreshold {grade: 'A', min_score: 20, min_food_per_100_moves: 4, max_turns_per_food: 3, max_ticks_per_food: 250},
+    GradeThreshold {grade: 'B', min_score: 10, min_food_per_100_moves: 3, max_turns_per_food: 4, max_ticks_per_food: 400},
+    GradeThreshold {grade: 'C', min_score: 5, min_food_per_100_moves: 2, max_turns_per_food: 6, max_ticks_per_food: 800},
+];
 
-const START: &'static str =
-    "################################################################################
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                       <                                      #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                  *                                                           #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     #                                                                              #
-     ################################################################################";
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum ZoneKind {
+    Slow,
+    NoTurn,
+    DoublePoints,
+}
 
-impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH, HEIGHT> {
-    pub fn new() -> Self {
-        let mut game = SnakeGame {
-            cells: [[Cell::Food; WIDTH]; HEIGHT],
-            snake: Snake::new(Position { col: 0, row: 0 }, '>'),
-            food_eaten: 0,
-            countdown: UPDATE_FREQUENCY,
-            last_key: None,
-            status: Status::Normal,
-        };
-        game.reset();
-        game
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct Zone<const WIDTH: usize, const HEIGHT: usize> {
+    top_left: Position<WIDTH,HEIGHT>, width: usize, height: usize, kind: ZoneKind
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> Zone<WIDTH,HEIGHT> {
+    pub fn new(top_left: Position<WIDTH,HEIGHT>, width: usize, height: usize, kind: ZoneKind) -> Self {
+        Zone {top_left, width, height, kind}
     }
 
-    fn reset(&mut self) {
-        for (row, row_chars) in START.split('\n').enumerate() {
-            for (col, icon) in row_chars.trim().chars().enumerate() {
-                self.translate_icon(row, col, icon);
-            }
-        }
-        self.status = Status::Normal;
-        self.food_eaten = 0;
-        self.last_key = None;
+    pub fn kind(&self) -> ZoneKind {
+        self.kind
     }
 
-    pub fn score(&self) -> u32 {
-        self.food_eaten
+    pub fn contains(&self, p: Position<WIDTH,HEIGHT>) -> bool {
+        let (row, col) = p.row_col();
+        let (top, left) = self.top_left.row_col();
+        p.is_legal() && top <= row && row < top + self.height && left <= col && col < left + self.width
     }
+}
 
-    fn translate_icon(&mut self, row: usize, col: usize, icon: char) {
-        match icon {
-            '#' => self.cells[row][col] = Cell::Wall,
-            '*' => self.cells[row][col] = Cell::Food,
-            '>' | '<' | '^' | 'v' => {
-                self.snake = Snake::new(Position { row: row as i16, col: col as i16 }, icon);
-            },
-            ' ' => self.cells[row][col] = Cell::Empty,
-            'o' => self.cells[row][col] = Cell::Body,
-            _ => panic!("Unrecognized character: '{}'", icon)
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct GameConfig {
+    pub win_threshold: u32,
+    pub food_target: usize,
+    pub lives: u32,
+    pub speed: usize,
+    pub board_mode: BoardMode,
+    pub hunger: Option<u32>,
+    pub tron: bool,
+    pub layout: Layout,
+    pub shrink_seconds: Option<u32>,
+    pub obstacle_every: Option<u32>,
+    pub fog_radius: Option<usize>,
+    pub time_attack_seconds: Option<u32>,
+    pub endless: bool,
+    pub seed: Option<u32>,
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum Layout {
+    Levels,
+    Maze,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            win_threshold: DEFAULT_WIN_THRESHOLD,
+            food_target: DEFAULT_FOOD_TARGET,
+            lives: DEFAULT_LIVES,
+            speed: UPDATE_FREQUENCY,
+            board_mode: BoardMode::Walled,
+            hunger: None,
+            tron: false,
+            layout: Layout::Levels,
+            shrink_seconds: None,
+            obstacle_every: None,
+            fog_radius: None,
+            time_attack_seconds: None,
+            endless: false,
+            seed: None
         }
     }
+}
 
-    pub fn cell(&self, p: Position<WIDTH, HEIGHT>) -> Cell {
-        self.cells[p.row as usize][p.col as usize]
-    }
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+struct Wanderer<const WIDTH: usize, const HEIGHT: usize> {
+    pos: Position<WIDTH,HEIGHT>, cooldown: u32
+}
 
-    pub fn cell_pos_iter(&self) -> RowColIter<WIDTH, HEIGHT> {
-        RowColIter { row: 0, col: 0 }
-    }
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+struct Popup<const WIDTH: usize, const HEIGHT: usize> {
+    pos: Position<WIDTH,HEIGHT>, points: u32, until: u64
+}
 
-    pub fn snake_at(&self) -> Position<WIDTH, HEIGHT> {
-        self.snake.pos
-    }
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum OverCause {
+    Crashed,
+    Starved,
+    Caught,
+    TimeUp,
+}
 
-    pub fn snake_icon(&self) -> char {
-        self.snake.icon()
-    }
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
 
-    pub fn update(&mut self) {
-        self.resolve_move();
-        self.last_key = None;
-        self.snake.tick();
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard"
+        }
     }
 
-    fn ahead_left_right(&self, p: Position<WIDTH, HEIGHT>, dir: Dir) -> (Cell, Cell, Cell) {
-        let ahead = self.cell(p.neighbor(dir));
-        let left = self.cell(p.neighbor(dir.left()));
-        let right = self.cell(p.neighbor(dir.right()));
-        (ahead, left, right)
+    fn base_frequency(&self) -> usize {
+        match self {
+            Difficulty::Easy => UPDATE_FREQUENCY + 1,
+            Difficulty::Normal => UPDATE_FREQUENCY,
+            Difficulty::Hard => UPDATE_FREQUENCY - 1
+        }
     }
 
-    pub fn countdown_complete(&mut self) -> bool {
-        if self.countdown == 0 {
-            self.countdown = UPDATE_FREQUENCY;
-            true
-        } else {
-            self.countdown -= 1;
-            false
+    // Moves between eating a pellet and its replacement appearing.
+    fn spawn_delay(&self) -> u32 {
+        match self {
+            Difficulty::Easy | Difficulty::Normal => 0,
+            Difficulty::Hard => HARD_SPAWN_DELAY
         }
     }
 
-    pub fn key(&mut self, key: DecodedKey) {
-        match self.status {
-            Status::Over => {
-                match key {
-                    DecodedKey::RawKey(KeyCode::S) | DecodedKey::Unicode('s') => self.reset(),
-                    _ => {}
-                }
-            }
-            Status::Normal => {
-                match key{
-                    DecodedKey::RawKey(KeyCode::O) | DecodedKey::Unicode('o') => {
-                        self.status = Status::Over
-                    },
-                    DecodedKey::RawKey(KeyCode::I) | DecodedKey::Unicode('i') => {
-                        self.cells[15][40] = Cell::Food;
-                    },
-                    _ => {let key = match key {
-                        DecodedKey::RawKey(k) => match k {
-                            KeyCode::ArrowUp => Some(Dir::N),
-                            KeyCode::ArrowDown => Some(Dir::S),
-                            KeyCode::ArrowLeft => Some(Dir::W),
-                            KeyCode::ArrowRight => Some(Dir::E),
-                            _ => None
-                        }
-                        DecodedKey::Unicode(c) => match c {
-                            'w' => Some(Dir::N),
-                            'a' => Some(Dir::W),
-                            's' => Some(Dir::S),
-                            'd' => Some(Dir::E),
-                            _ => None
-                        }
-                    };
-                        if key.is_some() {
-                            self.last_key = key;
-                        }}
-                }
-            }
+    // One in this many meals also drops a poison cell somewhere.
+    fn poison_chance(&self) -> Option<usize> {
+        match self {
+            Difficulty::Easy => None,
+            Difficulty::Normal => Some(8),
+            Difficulty::Hard => Some(3)
         }
     }
 
-    fn resolve_move(&mut self) {
-        if let Some(dir) = self.last_key {
-            let neighbor = self.snake.pos.neighbor(dir);
-            if neighbor.is_legal() {
-                let (row, col) = neighbor.row_col();
-                if self.cells[row][col] != Cell::Wall {
-                    self.move_to(neighbor, dir);
-                }
-
-                if self.cells[row][col] == Cell::Wall || self.cells[row][col] == Cell::Body {
-                    self.status = Status::Over;
-                }
-
-            }
+    fn win_threshold(&self) -> u32 {
+        match self {
+            Difficulty::Easy => 20,
+            Difficulty::Normal => DEFAULT_WIN_THRESHOLD,
+            Difficulty::Hard => 40
         }
     }
+}
 
-    fn move_to(&mut self, neighbor: Position<WIDTH, HEIGHT>, dir: Dir) {
-        let testnum_col = 80;
-        let testnum_row = 20;
-        self.snake.pos = neighbor;
-        self.snake.dir = dir;
-        let (row, col) = neighbor.row_col();
-        let mut change = self.score() + 5;
-        match self.cells[row][col] {
-            Cell::Food => {
-                self.food_eaten += 1;
-                if self.food_eaten >= 30{
-                    self.status = Status::Over;
-                }
-                self.cells[row][col] = Cell::Empty;
-                change += 333;
-                let mut multiple_col = (&testnum_col - ((&change * self.score()) % testnum_col));
-                if multiple_col == 80 {
-                    multiple_col -= 33;
-                }
-                let mut multiple_row = (&testnum_row) - ((&change * self.score()) % testnum_row);
-                self.cells[multiple_row as usize][multiple_col as usize] = Cell::Food;
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum RampKind {
+    Flat,
+    Linear,
+    Stepped,
+    Exponential,
+}
 
-            }
-            _ => {}
+impl RampKind {
+    fn frequency(&self, base: usize, score: u32) -> usize {
+        match self {
+            RampKind::Flat => base,
+            RampKind::Linear => base.saturating_sub((score / RAMP_LINEAR_FOOD) as usize),
+            RampKind::Stepped => base.saturating_sub((score / RAMP_STEP) as usize),
+            RampKind::Exponential => base.checked_shr(score / RAMP_STEP).unwrap_or(0)
         }
     }
 
-    pub fn status(&self) -> Status {
-        self.status
+    fn food_per_step(&self) -> Option<u32> {
+        match self {
+            RampKind::Flat => None,
+            RampKind::Linear => Some(RAMP_LINEAR_FOOD),
+            RampKind::Stepped | RampKind::Exponential => Some(RAMP_STEP)
+        }
     }
+}
 
-    fn key2dir(key: DecodedKey) -> Option<Dir> {
-        match key {
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum BoardMode {
+    Walled,
+    Wrapping,
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+enum MapSource<const WIDTH: usize, const HEIGHT: usize> {
+    Text(&'static str),
+    Grid(&'static [[char; WIDTH]; HEIGHT]),
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+struct Body<const WIDTH: usize, const HEIGHT: usize> {
+    segments: [Position<WIDTH,HEIGHT>; MAX_BODY], start: usize, len: usize
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> Body<WIDTH,HEIGHT> {
+    fn new() -> Self {
+        Body {segments: [Position {col: 0, row: 0}; MAX_BODY], start: 0, len: 0}
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == MAX_BODY
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn push_front(&mut self, p: Position<WIDTH,HEIGHT>) {
+        self.start = (self.start + MAX_BODY - 1) % MAX_BODY;
+        self.segments[self.start] = p;
+        self.len += 1;
+    }
+
+    fn push_back(&mut self, p: Position<WIDTH,HEIGHT>) {
+        self.segments[(self.start + self.len) % MAX_BODY] = p;
+        self.len += 1;
+    }
+
+    fn pop_back(&mut self) -> Option<Position<WIDTH,HEIGHT>> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(self.segments[(self.start + self.len) % MAX_BODY])
+        }
+    }
+
+    fn tail(&self) -> Option<Position<WIDTH,HEIGHT>> {
+        if self.len == 0 {
+            None
+        } else {
+            Some(self.segments[(self.start + self.len - 1) % MAX_BODY])
+        }
+    }
+
+    // Counted from the head end.
+    fn get(&self, i: usize) -> Position<WIDTH,HEIGHT> {
+        self.segments[(self.start + i) % MAX_BODY]
+    }
+
+    fn iter(&self) -> impl Iterator<Item=Position<WIDTH,HEIGHT>> + '_ {
+        (0..self.len).map(move |i| self.get(i))
+    }
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+struct InputQueue {
+    dirs: [Dir; INPUT_QUEUE_LEN], len: usize
+}
+
+impl InputQueue {
+    fn new() -> Self {
+        InputQueue {dirs: [Dir::N; INPUT_QUEUE_LEN], len: 0}
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn front(&self) -> Option<Dir> {
+        self.dirs[..self.len].first().copied()
+    }
+
+    fn back(&self) -> Option<Dir> {
+        self.dirs[..self.len].last().copied()
+    }
+
+    fn push(&mut self, d: Dir) {
+        if self.len < INPUT_QUEUE_LEN {
+            self.dirs[self.len] = d;
+            self.len += 1;
+        }
+    }
+
+    fn pop(&mut self) -> Option<Dir> {
+        let front = self.front();
+        if front.is_some() {
+            self.dirs.copy_within(1..self.len, 0);
+            self.len -= 1;
+        }
+        front
+    }
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum Status {
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+    Victory,
+    Error,
+}
+
+const SNAKE_START_DIR: [Dir; 4] = [Dir::E, Dir::W, Dir::E, Dir::W];
+const ALL_DIRS: [Dir; 4] = [Dir::N, Dir::E, Dir::S, Dir::W];
+const NO_TILE: char = '_';
+const EATEN_HISTORY: usize = 32;
+const MAX_ZONES: usize = 4;
+const ZONE_SLOWDOWN: usize = 2;
+const FOOD_POINTS: u32 = 1;
+const TOAST_LEN: usize = 40;
+// The PIT is left at its power-on rate of ~18.2 Hz.
+const TICKS_PER_MINUTE: u64 = 1092;
+
+const START: &'static str =
+    "################################################################################
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                       <                                      #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                  *                                                           #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     ################################################################################";
+
+const LEVEL_2: &'static str =
+    "################################################################################
+     #                                                                              #
+     #                                                                              #
+     #                                       *                                      #
+     #                                                                              #
+     #                                                                              #
+     #              ##################################################              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                      <                                       #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #              ##################################################              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     #                                                                              #
+     ################################################################################";
+
+const LEVEL_3: &'static str =
+    "################################################################################
+     #                                                                              #
+     #                                                                              #
+     #                                       #                                      #
+     #                                       #                                      #
+     #                   ###                 #                   ###                #
+     #                   ###                 #                   ###                #
+     #                                       #                                      #
+     #                                       #                                      #
+     #                             <                                                #
+     #                                                                              #
+     #         ##########################        ##########################         #
+     #                                                                              #
+     #                                                                              #
+     #                                       #                                      #
+     #                                       #         *                            #
+     #                                       #                                      #
+     #                   ###                 #                   ###                #
+     #                   ###                 #                   ###                #
+     #                                       #                                      #
+     #                                                                              #
+     #                                                                              #
+     ################################################################################";
+
+const STAGES: [Stage; 3] = [
+    Stage {map: START, food_goal: 8},
+    Stage {map: LEVEL_2, food_goal: 10},
+    Stage {map: LEVEL_3, food_goal: 12},
+];
+
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH, HEIGHT> {
+    pub fn new(config: GameConfig) -> Self {
+        Self::from_source(MapSource::Text(STAGES[0].map), config, &STAGES)
+    }
+
+    #[cfg(feature = "bench")]
+    pub fn stress() -> Self {
+        let mut game = Self::new(GameConfig::default());
+        let (head_row, _) = game.snake.pos.row_col();
+        for p in game.cell_pos_iter() {
+            let (row, col) = p.row_col();
+            if game.cells[row][col] == Cell::Empty && row != head_row {
+                game.cells[row][col] = if col % 8 == 0 {
+                    Cell::Food
+                } else if (row + col) % 13 == 0 {
+                    Cell::Wall
+                } else {
+                    Cell::Body
+                };
+            }
+        }
+        game.recount_food();
+        game.start();
+        game
+    }
+
+    pub fn with_board_mode(board_mode: BoardMode) -> Self {
+        Self::new(GameConfig {board_mode, ..GameConfig::default()})
+    }
+
+    pub fn with_map(map: &'static str) -> Self {
+        Self::from_source(MapSource::Text(map), GameConfig::default(), &[])
+    }
+
+    pub fn from_chars(grid: &'static [[char; WIDTH]; HEIGHT]) -> Self {
+        Self::from_source(MapSource::Grid(grid), GameConfig::default(), &[])
+    }
+
+    fn from_source(map: MapSource<WIDTH, HEIGHT>, config: GameConfig, stages: &'static [Stage]) -> Self {
+        assert!(config.lives > 0, "A game needs at least one life");
+        let mut game = SnakeGame {
+            cells: [[Cell::Food; WIDTH]; HEIGHT],
+            background: [[NO_TILE; WIDTH]; HEIGHT],
+            board_color: DEFAULT_BOARD_COLOR,
+            map,
+            config,
+            campaign: Campaign::new(stages),
+            current_level: 0,
+            level_food: 0,
+            level_time_limit: None,
+            level_started: 0,
+            score_rank: None,
+            save_pending: false,
+            combo: 0,
+            last_meal: 0,
+            popups: [None; MAX_POPUPS],
+            achievements: Achievements::new(),
+            straight_food: 0,
+            demo: false,
+            idle_frames: 0,
+            replay: replay::Replay::new(Rng::new(0)),
+            playback: None,
+            run_seed: 0,
+            seed_entry: None,
+            map_name: None,
+            map_par: None,
+            level_error: None,
+            snake: Snake::new(Position { col: 0, row: 0 }, '>'),
+            start: Snake::new(Position { col: 0, row: 0 }, '>'),
+            body: Body::new(),
+            pending_growth: 0,
+            food_at_tail: false,
+            food_eaten: 0,
+            points: 0,
+            breakdown: ScoreBreakdown::default(),
+            zones: [None; MAX_ZONES],
+            countdown: config.speed,
+            difficulty: Difficulty::Normal,
+            spawn_delay: 0,
+            food_on_board: 0,
+            bonus: None,
+            effects: Effects::new(),
+            hunger_left: config.hunger.unwrap_or(0),
+            wandering_food: false,
+            wanderers: [None; MAX_WANDERERS],
+            hazards_frozen: false,
+            rival_enabled: false,
+            rival: None,
+            two_player: false,
+            winner: None,
+            keys_held: 0,
+            movers: [None; MAX_MOVERS],
+            mover_countdown: MOVER_PERIOD,
+            enemies: [None; MAX_ENEMIES],
+            enemy_turn: false,
+            arena_inset: 0,
+            next_shrink: 0,
+            random_turn_on_eat: false,
+            over_cause: OverCause::Crashed,
+            lives: config.lives,
+            invulnerable: 0,
+            inputs: InputQueue::new(),
+            rng: Rng::new(prng::tsc_seed()),
+            magnetic_walls: false,
+            ramp: RampKind::Stepped,
+            pedal_mode: false,
+            pedal_held: false,
+            rotation: Rotation::Normal,
+            moves: 0,
+            turns: 0,
+            grades: &DEFAULT_GRADES,
+            cheat_codes: &DEFAULT_CHEAT_CODES,
+            code_matcher: cheat_codes::SequenceMatcher::new(),
+            rainbow: false,
+            #[cfg(feature = "debug-tools")]
+            debug: debug::DebugOverlay::new(),
+            eaten: [Position { col: 0, row: 0 }; EATEN_HISTORY],
+            eaten_count: 0,
+            frames: 0,
+            elapsed_ticks: 0,
+            paused_ticks: 0,
+            auto_restart: None,
+            over_frames: 0,
+            toast: [0; TOAST_LEN],
+            toast_len: 0,
+            toast_until: 0,
+            status: Status::Playing,
+        };
+        game.reset();
+        if game.status == Status::Playing {
+            game.status = Status::Menu;
+        }
+        game
+    }
+
+    // Restarts from whichever stage was picked on the menu.
+    fn reset(&mut self) {
+        self.reset_to_level(self.campaign.selected());
+    }
+
+    // Every run starts from a seed, picked at random unless one was set,
+    // so the seed shown afterwards is all it takes to get the same food.
+    pub fn reset_to_level(&mut self, level: usize) {
+        self.run_seed = match self.config.seed {
+            Some(seed) => seed,
+            None => {
+                self.rng.mix(self.frames);
+                self.rng.next_u64() as u32
+            }
+        };
+        self.rng = Rng::new(self.run_seed as u64);
+        self.replay.begin(self.rng, level, self.countdown);
+        self.restart_run(level);
+    }
+
+    // Everything from here on only depends on the PRNG and the moves made,
+    // which is what lets a replay reproduce the run.
+    fn restart_run(&mut self, level: usize) {
+        self.status = Status::Playing;
+        self.food_eaten = 0;
+        self.points = 0;
+        self.breakdown = ScoreBreakdown::default();
+        self.score_rank = None;
+        self.combo = 0;
+        self.last_meal = 0;
+        self.popups = [None; MAX_POPUPS];
+        self.straight_food = 0;
+        self.eaten_count = 0;
+        self.over_frames = 0;
+        self.elapsed_ticks = 0;
+        self.paused_ticks = 0;
+        self.lives = self.config.lives;
+        self.invulnerable = 0;
+        self.moves = 0;
+        self.turns = 0;
+        self.load_level(level);
+    }
+
+    fn load_level(&mut self, level: usize) {
+        if let Some(stage) = self.campaign.stage(level) {
+            self.map = MapSource::Text(stage.map);
+            self.current_level = level;
+        }
+        self.level_started = self.elapsed_ticks;
+        self.body.clear();
+        self.pending_growth = 0;
+        self.level_food = 0;
+        self.spawn_delay = 0;
+        self.bonus = None;
+        self.effects.clear();
+        self.feed();
+        self.wanderers = [None; MAX_WANDERERS];
+        self.inputs.clear();
+        let parsed = match (self.config.layout, self.map) {
+            (Layout::Maze, _) => Ok(maze::generate(&mut self.rng)),
+            (Layout::Levels, MapSource::Text(text)) => Level::parse(text),
+            (Layout::Levels, MapSource::Grid(grid)) => Level::from_chars(grid)
+        };
+        match parsed {
+            Ok(level) => {
+                self.cells = level.cells;
+                self.snake = level.start;
+                self.start = level.start;
+                self.map_name = level.name;
+                self.map_par = level.par;
+                self.level_time_limit = level.time_limit;
+                self.movers = level.movers;
+                self.mover_countdown = MOVER_PERIOD;
+                self.enemies = [None; MAX_ENEMIES];
+                self.enemy_turn = false;
+                for p in level.enemies.iter().flatten() {
+                    self.add_enemy(*p);
+                }
+                self.level_error = None;
+                self.arena_inset = 0;
+                self.next_shrink = self.elapsed_ticks + self.shrink_interval().unwrap_or(0);
+                if self.config.board_mode == BoardMode::Wrapping {
+                    self.open_border();
+                }
+                self.recount_food();
+                self.top_up_food();
+                self.rival = None;
+                self.winner = None;
+                self.keys_held = 0;
+                if self.rival_enabled {
+                    self.spawn_rival();
+                }
+            }
+            Err(error) => {
+                self.cells = [[Cell::Empty; WIDTH]; HEIGHT];
+                self.food_on_board = 0;
+                self.movers = [None; MAX_MOVERS];
+                self.enemies = [None; MAX_ENEMIES];
+                self.map_name = None;
+                self.map_par = None;
+                self.level_time_limit = None;
+                self.level_error = Some(error);
+                self.status = Status::Error;
+            }
+        }
+    }
+
+    pub fn level_error(&self) -> Option<LevelError> {
+        self.level_error
+    }
+
+    pub fn current_level(&self) -> usize {
+        self.current_level
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.campaign.len()
+    }
+
+    fn open_border(&mut self) {
+        for p in self.cell_pos_iter() {
+            let (row, col) = p.row_col();
+            if Self::in_border(row, col, 1) && self.cells[row][col] == Cell::Wall {
+                self.cells[row][col] = Cell::Empty;
+            }
+        }
+    }
+
+    pub fn board_mode(&self) -> BoardMode {
+        self.config.board_mode
+    }
+
+    fn next_pos(&self, p: Position<WIDTH, HEIGHT>, d: Dir) -> Position<WIDTH, HEIGHT> {
+        match self.config.board_mode {
+            BoardMode::Walled => p.neighbor(d),
+            BoardMode::Wrapping => p.wrapped_neighbor(d)
+        }
+    }
+
+    fn clear_body(&mut self) {
+        while let Some(p) = self.body.pop_back() {
+            let (row, col) = p.row_col();
+            self.cells[row][col] = Cell::Empty;
+        }
+        self.pending_growth = 0;
+    }
+
+    pub fn map_name(&self) -> Option<&str> {
+        self.map_name
+    }
+
+    pub fn map_par(&self) -> Option<u32> {
+        self.map_par
+    }
+
+    pub fn reset_snake_only(&mut self) {
+        self.clear_body();
+        self.snake = self.start;
+        self.status = Status::Playing;
+        self.over_frames = 0;
+        self.inputs.clear();
+    }
+
+    pub fn score(&self) -> u32 {
+        self.points
+    }
+
+    pub fn score_breakdown(&self) -> ScoreBreakdown {
+        self.breakdown
+    }
+
+    pub fn set_background(&mut self, layer: &str) {
+        self.background = [[NO_TILE; WIDTH]; HEIGHT];
+        for (row, row_chars) in layer.split('\n').enumerate() {
+            for (col, tile) in row_chars.trim().chars().enumerate() {
+                self.background[row][col] = tile;
+            }
+        }
+    }
+
+    // What every board cell is drawn on, and all an empty one shows.
+    pub fn board_color(&self) -> Color {
+        self.board_color
+    }
+
+    pub fn set_board_color(&mut self, color: Color) {
+        self.board_color = color;
+    }
+
+    pub fn background(&self, p: Position<WIDTH, HEIGHT>) -> Option<char> {
+        match self.background[p.row as usize][p.col as usize] {
+            NO_TILE => None,
+            tile => Some(tile)
+        }
+    }
+
+    pub fn cell(&self, p: Position<WIDTH, HEIGHT>) -> Cell {
+        self.cells[p.row as usize][p.col as usize]
+    }
+
+    pub fn set_cell(&mut self, p: Position<WIDTH, HEIGHT>, cell: Cell) -> bool {
+        let (row, col) = p.row_col();
+        if self.cells[row][col] == Cell::Bedrock {
+            false
+        } else {
+            let was_food = self.cells[row][col] == Cell::Food;
+            if was_food && cell != Cell::Food {
+                self.food_on_board -= 1;
+            } else if !was_food && cell == Cell::Food {
+                self.food_on_board += 1;
+            }
+            self.cells[row][col] = cell;
+            true
+        }
+    }
+
+    pub fn break_wall(&mut self, p: Position<WIDTH, HEIGHT>) -> bool {
+        self.cell(p) == Cell::Wall && self.set_cell(p, Cell::Empty)
+    }
+
+    // False, with the board left alone, when any part of the snake would
+    // end up inside the new border.
+    pub fn thicken_border(&mut self, thickness: usize) -> bool {
+        let buried = |p: Position<WIDTH, HEIGHT>| {
+            let (row, col) = p.row_col();
+            Self::in_border(row, col, thickness)
+        };
+        if buried(self.snake.pos) || self.body.iter().any(buried) {
+            return false;
+        }
+        for p in self.cell_pos_iter() {
+            let (row, col) = p.row_col();
+            if Self::in_border(row, col, thickness) {
+                self.set_cell(p, Cell::Wall);
+            }
+        }
+        true
+    }
+
+    fn in_border(row: usize, col: usize, thickness: usize) -> bool {
+        row < thickness || col < thickness || row + thickness >= HEIGHT || col + thickness >= WIDTH
+    }
+
+    pub fn positions_of(&self, cell: Cell) -> impl Iterator<Item=Position<WIDTH, HEIGHT>> + '_ {
+        self.cell_pos_iter().filter(move |p| self.cell(*p) == cell)
+    }
+
+    pub fn food_positions(&self) -> impl Iterator<Item=Position<WIDTH, HEIGHT>> + '_ {
+        self.positions_of(Cell::Food)
+    }
+
+    pub fn food_centroid(&self) -> Option<Position<WIDTH, HEIGHT>> {
+        let (mut rows, mut cols, mut count) = (0, 0, 0);
+        for p in self.food_positions() {
+            rows += p.row as usize;
+            cols += p.col as usize;
+            count += 1;
+        }
+        if count == 0 {
+            None
+        } else {
+            Some(Position {row: ((rows + count / 2) / count) as i16, col: ((cols + count / 2) / count) as i16})
+        }
+    }
+
+    pub fn open_neighbors(&self) -> DirList {
+        let mut open = DirList::new();
+        for d in ALL_DIRS.iter() {
+            let n = self.next_pos(self.snake.pos, *d);
+            if n.is_legal() && self.cell(n) == Cell::Empty {
+                open.push(*d);
+            }
+        }
+        open
+    }
+
+    pub fn occupancy_mask(&self, out: &mut [u64]) {
+        assert!(out.len() * 64 >= WIDTH * HEIGHT, "Mask needs {} bits, got {}", WIDTH * HEIGHT, out.len() * 64);
+        for word in out.iter_mut() {
+            *word = 0;
+        }
+        for p in self.cell_pos_iter() {
+            let (row, col) = p.row_col();
+            let occupied = match self.cells[row][col] {
+                Cell::Wall | Cell::Bedrock | Cell::Door | Cell::MovingWall | Cell::Body | Cell::RivalBody => true,
+                _ => p == self.snake.pos
+            };
+            if occupied {
+                let bit = row * WIDTH + col;
+                out[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+    }
+
+    pub fn largest_open_rect(&self) -> (Position<WIDTH, HEIGHT>, usize, usize) {
+        let mut heights = [0; WIDTH];
+        let mut best = (Position {row: 0, col: 0}, 0, 0);
+        for (row, row_cells) in self.cells.iter().enumerate() {
+            for (height, cell) in heights.iter_mut().zip(row_cells.iter()) {
+                *height = match cell {
+                    Cell::Wall | Cell::Bedrock | Cell::Door | Cell::MovingWall | Cell::Body | Cell::RivalBody => 0,
+                    _ => *height + 1
+                };
+            }
+            let mut stack = [0; WIDTH];
+            let mut top = 0;
+            for col in 0..=WIDTH {
+                let h = if col < WIDTH {heights[col]} else {0};
+                while top > 0 && heights[stack[top - 1]] >= h {
+                    let height = heights[stack[top - 1]];
+                    top -= 1;
+                    let left = if top == 0 {0} else {stack[top - 1] + 1};
+                    let width = col - left;
+                    if width * height > best.1 * best.2 {
+                        best = (Position {row: (row + 1 - height) as i16, col: left as i16}, width, height);
+                    }
+                }
+                if col < WIDTH {
+                    stack[top] = col;
+                    top += 1;
+                }
+            }
+        }
+        best
+    }
+
+    pub fn length(&self) -> usize {
+        1 + self.body.len()
+    }
+
+    pub fn empty_cell_count(&self) -> usize {
+        self.count_cells(Cell::Empty)
+    }
+
+    pub fn fill_fraction(&self) -> (usize, usize) {
+        let walls = self.cell_pos_iter().filter(|p| self.cell(*p).is_wall()).count();
+        (self.length(), WIDTH * HEIGHT - walls)
+    }
+
+    fn count_cells(&self, cell: Cell) -> usize {
+        self.positions_of(cell).count()
+    }
+
+    pub fn food_reachable(&self) -> bool {
+        let reached = self.flood_fill(self.snake.pos);
+        self.cell_pos_iter().any(|p| {
+            let (row, col) = p.row_col();
+            reached[row][col] && self.cells[row][col] == Cell::Food
+        })
+    }
+
+    fn passable(&self, p: Position<WIDTH, HEIGHT>) -> bool {
+        p.is_legal() && match self.cell(p) {
+            Cell::Wall | Cell::Bedrock | Cell::Door | Cell::MovingWall | Cell::Body | Cell::RivalBody => false,
+            _ => true
+        }
+    }
+
+    fn flood_fill(&self, start: Position<WIDTH, HEIGHT>) -> [[bool; WIDTH]; HEIGHT] {
+        let mut reached = [[false; WIDTH]; HEIGHT];
+        let (row, col) = start.row_col();
+        reached[row][col] = true;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for p in self.cell_pos_iter() {
+                let (row, col) = p.row_col();
+                if !reached[row][col] && self.passable(p) && ALL_DIRS.iter().any(|d| {
+                    let n = self.next_pos(p, *d);
+                    n.is_legal() && reached[n.row as usize][n.col as usize]
+                }) {
+                    reached[row][col] = true;
+                    changed = true;
+                }
+            }
+        }
+        reached
+    }
+
+    pub fn cell_pos_iter(&self) -> RowColIter<WIDTH, HEIGHT> {
+        RowColIter { row: 0, col: 0 }
+    }
+
+    // The fog lifts once the game ends so the whole board can be seen.
+    pub fn is_visible(&self, p: Position<WIDTH, HEIGHT>) -> bool {
+        match self.config.fog_radius {
+            Some(radius) if self.status == Status::Playing => rival::manhattan(p, self.snake.pos) <= radius,
+            _ => true
+        }
+    }
+
+    pub fn snake_at(&self) -> Position<WIDTH, HEIGHT> {
+        self.snake.pos
+    }
+
+    pub fn snake_icon(&self) -> char {
+        self.snake.icon()
+    }
+
+    // Effects are checked from highest to lowest precedence: the
+    // invulnerability color, then whatever the body is drawn in (ghost,
+    // then rainbow). The plain snake color only applies when none of them
+    // are active.
+    pub fn effective_head_color(&self) -> Color {
+        if self.is_invulnerable() {
+            Color::DarkGray
+        } else {
+            self.body_color_at(self.snake.pos)
+        }
+    }
+
+    pub fn effective_body_color(&self) -> Color {
+        if self.effects.is_active(Effect::Ghost) {
+            GHOST_COLOR
+        } else {
+            SNAKE_COLOR
+        }
+    }
+
+    pub fn body_color_at(&self, p: Position<WIDTH,HEIGHT>) -> Color {
+        if self.rainbow && !self.effects.is_active(Effect::Ghost) {
+            self.rainbow_color(p)
+        } else {
+            self.effective_body_color()
+        }
+    }
+
+    pub fn effects(&self) -> &Effects {
+        &self.effects
+    }
+
+    pub fn grant_effect(&mut self, effect: Effect) {
+        self.effects.grant(effect);
+    }
+
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    pub fn max_lives(&self) -> u32 {
+        self.config.lives
+    }
+
+    pub fn set_max_lives(&mut self, max_lives: u32) {
+        assert!(max_lives > 0, "A game needs at least one life");
+        self.config.lives = max_lives;
+        self.lives = max_lives;
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0 || self.is_god_mode()
+    }
+
+    pub fn hearts(&self, out: &mut [char]) -> usize {
+        let shown = out.len().min(self.config.lives as usize);
+        for (i, heart) in out[..shown].iter_mut().enumerate() {
+            *heart = if (i as u32) < self.lives {HEART} else {EMPTY_HEART};
+        }
+        shown
+    }
+
+    pub fn update(&mut self) {
+        if self.status == Status::Playing && !self.debug_hold() {
+            if self.demo {
+                if let Some(dir) = self.demo_dir() {
+                    self.queue_dir(dir);
+                }
+            }
+            let player_from = self.snake.pos;
+            self.resolve_move();
+            if self.status == Status::Playing {
+                self.update_rival(player_from);
+            }
+            self.invulnerable = self.invulnerable.saturating_sub(1);
+            self.effects.tick();
+            self.starve();
+            if self.wandering_food && !self.hazards_frozen {
+                self.wander_food();
+            }
+            if self.combo > 0 && self.elapsed_ticks - self.last_meal > COMBO_WINDOW {
+                self.combo = 0;
+            }
+            self.age_bonus();
+            self.slide_movers();
+            // Player two has already moved, so a crash into a mover has
+            // nothing left to wait for.
+            if let Some(winner) = self.winner.filter(|_| self.status == Status::Playing) {
+                self.finish_round(winner);
+            }
+            if self.status == Status::Playing {
+                self.update_enemies();
+            }
+            if let Some(interval) = self.shrink_interval() {
+                if self.elapsed_ticks >= self.next_shrink {
+                    self.next_shrink += interval;
+                    self.shrink_arena();
+                }
+            }
+            if self.spawn_delay > 0 {
+                self.spawn_delay -= 1;
+                if self.spawn_delay == 0 {
+                    self.top_up_food();
+                }
+            }
+            if !self.scripted() {
+                self.check_achievements();
+            }
+        }
+        self.snake.tick();
+    }
+
+    pub fn heading(&self) -> Dir {
+        self.inputs.front().unwrap_or(self.snake.dir)
+    }
+
+    pub fn cell_ahead(&self) -> Cell {
+        let ahead = self.next_pos(self.snake.pos, self.heading());
+        if ahead.is_legal() {
+            self.cell(ahead)
+        } else {
+            Cell::Wall
+        }
+    }
+
+    pub fn project_path(&self, steps: usize, out: &mut [Position<WIDTH, HEIGHT>]) -> usize {
+        let dir = self.heading();
+        let mut p = self.snake.pos;
+        let mut count = 0;
+        while count < steps && count < out.len() {
+            p = self.next_pos(p, dir);
+            if !p.is_legal() || self.cell(p).is_wall() {
+                break;
+            }
+            out[count] = p;
+            count += 1;
+        }
+        count
+    }
+
+    pub fn is_dir_safe(&self, d: Dir, lookahead: usize) -> bool {
+        let mut p = self.snake.pos;
+        for _ in 0..lookahead {
+            p = self.next_pos(p, d);
+            if !self.passable(p) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Looks where the next update will really go, which is not always the
+    // queued turn.
+    pub fn about_to_eat(&self) -> bool {
+        let (dir, _) = self.next_move();
+        let ahead = self.next_pos(self.snake.pos, dir);
+        self.status == Status::Playing && ahead.is_legal() && self.cell(ahead) == Cell::Food
+    }
+
+    pub fn magnetic_walls(&self) -> bool {
+        self.magnetic_walls
+    }
+
+    pub fn set_magnetic_walls(&mut self, magnetic_walls: bool) {
+        self.magnetic_walls = magnetic_walls;
+    }
+
+    fn ahead_left_right(&self, p: Position<WIDTH, HEIGHT>, dir: Dir) -> (Cell, Cell, Cell) {
+        let ahead = self.cell(self.next_pos(p, dir));
+        let left = self.cell(self.next_pos(p, dir.left()));
+        let right = self.cell(self.next_pos(p, dir.right()));
+        (ahead, left, right)
+    }
+
+    pub fn ramp(&self) -> RampKind {
+        self.ramp
+    }
+
+    pub fn set_ramp(&mut self, ramp: RampKind) {
+        self.ramp = ramp;
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.config.speed = difficulty.base_frequency();
+        self.config.win_threshold = difficulty.win_threshold();
+    }
+
+    // Endless runs also pile up obstacles as the score climbs.
+    pub fn set_endless(&mut self, endless: bool) {
+        self.config.endless = endless;
+        self.config.obstacle_every = if endless {Some(ENDLESS_OBSTACLE_EVERY)} else {None};
+    }
+
+    // None goes back to a random seed each run.
+    pub fn set_seed(&mut self, seed: Option<u32>) {
+        self.config.seed = seed;
+        if self.status == Status::Menu {
+            self.reset();
+            if self.status == Status::Playing {
+                self.status = Status::Menu;
+            }
+        }
+    }
+
+    pub fn run_seed(&self) -> u32 {
+        self.run_seed
+    }
+
+    pub fn seed_entry(&self) -> Option<u32> {
+        self.seed_entry
+    }
+
+    // Digits typed after G on the menu; Enter keeps them (0 for random)
+    // and Escape drops them.
+    fn seed_entry_key(&mut self, key: DecodedKey) {
+        let typed = self.seed_entry.unwrap_or(0);
+        match key {
+            DecodedKey::Unicode(c) if c.is_ascii_digit() => {
+                let digit = c as u32 - '0' as u32;
+                if let Some(next) = typed.checked_mul(10).and_then(|n| n.checked_add(digit)) {
+                    self.seed_entry = Some(next);
+                }
+            }
+            DecodedKey::RawKey(KeyCode::Backspace) | DecodedKey::Unicode('\u{8}') => self.seed_entry = Some(typed / 10),
+            DecodedKey::RawKey(KeyCode::Escape) | DecodedKey::Unicode('\u{1b}') => self.seed_entry = None,
+            DecodedKey::RawKey(KeyCode::Enter) | DecodedKey::Unicode('\n') => {
+                self.seed_entry = None;
+                self.set_seed(Some(typed).filter(|seed| *seed != 0));
+            }
+            _ => {}
+        }
+    }
+
+    // Endless and time attack runs only end when the snake dies or time
+    // runs out.
+    pub fn has_win_target(&self) -> bool {
+        !self.config.endless && self.config.time_attack_seconds.is_none()
+    }
+
+    pub fn config(&self) -> GameConfig {
+        self.config
+    }
+
+    pub fn base_frequency(&self) -> usize {
+        self.config.speed
+    }
+
+    pub fn set_base_frequency(&mut self, base_frequency: usize) {
+        self.config.speed = base_frequency;
+    }
+
+    // Starts at 1 and goes up by one each time the ramp shortens the tick divider.
+    pub fn speed_level(&self) -> usize {
+        self.config.speed - self.ramp.frequency(self.config.speed, self.food_eaten) + 1
+    }
+
+    pub fn update_frequency(&self) -> usize {
+        let frequency = self.ramp.frequency(self.config.speed, self.food_eaten);
+        let frequency = if self.pedal_mode && !self.pedal_held {
+            frequency * PEDAL_SLOWDOWN
+        } else {
+            frequency
+        };
+        let frequency = match self.active_zone() {
+            Some(ZoneKind::Slow) => frequency * ZONE_SLOWDOWN,
+            _ => frequency
+        };
+        if self.effects.is_active(Effect::SlowMotion) {
+            frequency.max(1) * SLOW_MOTION_FACTOR
+        } else {
+            frequency
+        }
+    }
+
+    // Zero when the current ramp has no further speed-ups left.
+    pub fn food_until_speedup(&self) -> u32 {
+        match self.ramp.food_per_step() {
+            Some(step) if self.ramp.frequency(self.config.speed, self.food_eaten) > 0 => step - self.food_eaten % step,
+            _ => 0
+        }
+    }
+
+    pub fn set_pedal_mode(&mut self, pedal_mode: bool) {
+        self.pedal_mode = pedal_mode;
+    }
+
+    pub fn press_pedal(&mut self) {
+        self.pedal_held = true;
+    }
+
+    pub fn release_pedal(&mut self) {
+        self.pedal_held = false;
+    }
+
+    pub fn pedal_active(&self) -> bool {
+        self.pedal_mode && self.pedal_held
+    }
+
+    pub fn advance_frame(&mut self) {
+        self.frames += 1;
+        match self.status {
+            Status::Playing if self.debug_frozen() => {}
+            Status::Playing => self.elapsed_ticks += 1,
+            Status::Paused => self.paused_ticks += 1,
+            Status::Menu => {
+                self.idle_frames += 1;
+                if self.idle_frames >= seconds_to_ticks(ATTRACT_IDLE_SECONDS) {
+                    self.start_demo();
+                }
+            }
+            _ => {}
+        }
+        if self.status == Status::Playing && (self.time_left() == Some(0) || self.level_time_remaining() == Some(0)) {
+            self.end_game(OverCause::TimeUp);
+        }
+        if self.is_finished() {
+            self.playback = None;
+            self.over_frames += 1;
+            if self.demo {
+                if self.over_frames >= DEMO_OVER_FRAMES {
+                    self.end_demo();
+                }
+            } else if let Some(delay) = self.auto_restart {
+                if self.over_frames >= delay {
+                    self.reset();
+                }
+            }
+        }
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn elapsed_ticks(&self) -> u64 {
+        self.elapsed_ticks
+    }
+
+    // Ticks left before the current level's own time limit, if its map
+    // metadata sets one.
+    pub fn level_time_remaining(&self) -> Option<u64> {
+        self.level_time_limit
+            .map(|seconds| seconds_to_ticks(seconds).saturating_sub(self.elapsed_ticks - self.level_started))
+    }
+
+    // Ticks left in a time attack run.
+    pub fn time_left(&self) -> Option<u64> {
+        self.config.time_attack_seconds
+            .map(|seconds| seconds_to_ticks(seconds).saturating_sub(self.elapsed_ticks))
+    }
+
+    pub fn paused_ticks(&self) -> u64 {
+        self.paused_ticks
+    }
+
+    pub fn set_paused_ticks(&mut self, paused_ticks: u64) {
+        self.paused_ticks = paused_ticks;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        match self.status {
+            Status::Playing => self.status = Status::Paused,
+            Status::Paused => self.status = Status::Playing,
+            _ => {}
+        }
+    }
+
+    pub fn score_rate(&self) -> u32 {
+        if self.elapsed_ticks == 0 {
+            0
+        } else {
+            (self.score() as u64 * TICKS_PER_MINUTE / self.elapsed_ticks) as u32
+        }
+    }
+
+    pub fn show_toast(&mut self, text: &str, frames: u64) {
+        self.show_toast_parts(&[text], frames);
+    }
+
+    // The parts are joined and cut off at TOAST_LEN bytes.
+    fn show_toast_parts(&mut self, parts: &[&str], frames: u64) {
+        self.toast_len = 0;
+        for part in parts {
+            let mut len = part.len().min(TOAST_LEN - self.toast_len);
+            while !part.is_char_boundary(len) {
+                len -= 1;
+            }
+            self.toast[self.toast_len..self.toast_len + len].copy_from_slice(&part.as_bytes()[..len]);
+            self.toast_len += len;
+        }
+        self.toast_until = self.frames + frames;
+    }
+
+    pub fn achievements(&self) -> &Achievements {
+        &self.achievements
+    }
+
+    fn check_achievements(&mut self) {
+        let earned = [
+            (Achievement::FirstBite, self.food_eaten > 0),
+            (Achievement::StraightShooter, self.straight_food >= STRAIGHT_SHOOTER_FOOD),
+            (Achievement::ComboFive, self.combo >= COMBO_ACHIEVEMENT),
+            (Achievement::Flawless, self.status == Status::Victory && self.lives == self.config.lives),
+            (Achievement::Marathon, self.food_eaten >= MARATHON_FOOD),
+        ];
+        let mut fresh = false;
+        for (achievement, done) in earned.iter() {
+            if *done && self.achievements.unlock(*achievement) {
+                self.show_toast_parts(&["Achievement: ", achievement.name()], ACHIEVEMENT_TOAST_FRAMES);
+                fresh = true;
+            }
+        }
+        // The run's save was already queued when it ended.
+        if fresh && self.is_finished() {
+            self.save_pending = true;
+        }
+    }
+
+    pub fn toast(&self) -> Option<&str> {
+        if self.frames < self.toast_until {
+            core::str::from_utf8(&self.toast[..self.toast_len]).ok()
+        } else {
+            None
+        }
+    }
+
+    pub fn set_grade_thresholds(&mut self, grades: &'static [GradeThreshold]) {
+        self.grades = grades;
+    }
+
+    pub fn grade(&self) -> char {
+        if self.food_eaten == 0 || self.moves == 0 {
+            return 'D';
+        }
+        let efficiency = self.food_eaten * 100 / self.moves;
+        let turns = self.turns / self.food_eaten;
+        let time = self.elapsed_ticks / self.food_eaten as u64;
+        self.grades.iter()
+            .find(|t| self.score() >= t.min_score
+                && efficiency >= t.min_food_per_100_moves
+                && turns <= t.max_turns_per_food
+                && time <= t.max_ticks_per_food)
+            .map_or('D', |t| t.grade)
+    }
+
+    pub fn set_auto_restart(&mut self, frames: Option<u64>) {
+        self.auto_restart = frames;
+    }
+
+    pub fn countdown_complete(&mut self) -> bool {
+        if self.countdown == 0 {
+            self.countdown = self.update_frequency();
+            true
+        } else {
+            self.countdown -= 1;
+            false
+        }
+    }
+
+    pub fn key(&mut self, key: DecodedKey) {
+        if self.demo {
+            self.end_demo();
+            return;
+        }
+        if self.playback.is_some() {
+            self.stop_playback();
+            return;
+        }
+        self.idle_frames = 0;
+        if self.debug_key(key) {
+            return;
+        }
+        match self.status {
+            Status::Error => {}
+            Status::Menu if self.seed_entry.is_some() => self.seed_entry_key(key),
+            Status::Menu => {
+                self.check_cheat_codes(key);
+                match key {
+                    DecodedKey::RawKey(KeyCode::Enter) | DecodedKey::Unicode('\n') => self.start_selected(),
+                    DecodedKey::Unicode('1') => self.set_difficulty(Difficulty::Easy),
+                    DecodedKey::Unicode('2') => self.set_difficulty(Difficulty::Normal),
+                    DecodedKey::Unicode('3') => self.set_difficulty(Difficulty::Hard),
+                    DecodedKey::RawKey(KeyCode::M) | DecodedKey::Unicode('m') => self.set_endless(!self.config.endless),
+                    DecodedKey::Unicode('[') => self.campaign.select_previous(),
+                    DecodedKey::Unicode(']') => self.campaign.select_next(),
+                    DecodedKey::RawKey(KeyCode::G) | DecodedKey::Unicode('g') => self.seed_entry = Some(0),
+                    _ => {}
+                }
+            }
+            Status::Paused => {
+                match key {
+                    DecodedKey::RawKey(KeyCode::P) | DecodedKey::Unicode('p') => self.toggle_pause(),
+                    _ => {}
+                }
+            }
+            Status::GameOver | Status::Victory => {
+                match key {
+                    DecodedKey::RawKey(KeyCode::S) | DecodedKey::Unicode('s') => self.reset(),
+                    DecodedKey::RawKey(KeyCode::R) | DecodedKey::Unicode('r') => {
+                        self.start_playback();
+                    }
+                    DecodedKey::RawKey(KeyCode::F9) => {
+                        self.quick_load();
+                    }
+                    _ => {}
+                }
+            }
+            Status::Playing => {
+                match key{
+                    DecodedKey::RawKey(KeyCode::F5) => {
+                        self.quick_save();
+                    }
+                    DecodedKey::RawKey(KeyCode::F9) => {
+                        self.quick_load();
+                    }
+                    DecodedKey::RawKey(KeyCode::P) | DecodedKey::Unicode('p') => self.toggle_pause(),
+                    DecodedKey::RawKey(KeyCode::Q) | DecodedKey::Unicode('q') => self.turn_left(),
+                    DecodedKey::RawKey(KeyCode::E) | DecodedKey::Unicode('e') => self.turn_right(),
+                    DecodedKey::Unicode(c) if self.two_player && wasd_dir(c).is_some() => {
+                        if let Some(dir) = wasd_dir(c).map(|d| self.rotation.apply(d)) {
+                            self.queue_rival_dir(dir);
+                        }
+                    }
+                    _ => {let key = match key {
+                        DecodedKey::RawKey(k) => match k {
+                            KeyCode::ArrowUp => Some(Dir::N),
+                            KeyCode::ArrowDown => Some(Dir::S),
+                            KeyCode::ArrowLeft => Some(Dir::W),
+                            KeyCode::ArrowRight => Some(Dir::E),
+                            _ => None
+                        }
+                        DecodedKey::Unicode(c) => wasd_dir(c)
+                    };
+                        if let Some(dir) = key.map(|d| self.rotation.apply(d)) {
+                            self.queue_dir(dir);
+                        }}
+                }
+            }
+        }
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    pub fn set_screen_rotation(&mut self, rot: Rotation) {
+        self.rotation = rot;
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    pub fn spawn_food(&mut self) -> Option<Position<WIDTH, HEIGHT>> {
+        let spot = self.random_empty_cell();
+        if let Some(p) = spot {
+            let (row, col) = p.row_col();
+            self.cells[row][col] = Cell::Food;
+            self.food_on_board += 1;
+        }
+        spot
+    }
+
+    pub fn food_count(&self) -> usize {
+        self.food_on_board
+    }
+
+    fn recount_food(&mut self) {
+        self.food_on_board = self.food_positions().count();
+    }
+
+    pub fn bonus_ticks_left(&self) -> Option<u32> {
+        self.bonus.map(|(_, left)| left)
+    }
+
+    // Blinks on alternate animation frames once the bonus is about to expire.
+    pub fn bonus_blink_off(&self) -> bool {
+        self.bonus_ticks_left().map_or(false, |left| left <= BONUS_BLINK && !self.snake.open)
+    }
+
+    fn maybe_spawn_bonus(&mut self) {
+        if self.hazards_frozen {
+            return;
+        }
+        if self.bonus.is_none() && self.rng.below(BONUS_CHANCE) == 0 {
+            if let Some(p) = self.random_empty_cell() {
+                let (row, col) = p.row_col();
+                self.cells[row][col] = Cell::BonusFood;
+                self.bonus = Some((p, BONUS_LIFETIME));
+            }
+        }
+    }
+
+    fn age_bonus(&mut self) {
+        if let Some((p, left)) = self.bonus {
+            if left <= 1 {
+                let (row, col) = p.row_col();
+                if self.cells[row][col] == Cell::BonusFood {
+                    self.cells[row][col] = Cell::Empty;
+                }
+                self.bonus = None;
+            } else {
+                self.bonus = Some((p, left - 1));
+            }
+        }
+    }
+
+    fn eat_bonus(&mut self, p: Position<WIDTH, HEIGHT>) {
+        let (row, col) = p.row_col();
+        self.cells[row][col] = Cell::Empty;
+        self.bonus = None;
+        self.breakdown.base += BONUS_POINTS;
+        self.points += BONUS_POINTS;
+        self.show_popup(p, BONUS_POINTS);
+    }
+
+    // Reuses the slot that expires soonest, so a free or stale one goes first.
+    fn show_popup(&mut self, pos: Position<WIDTH, HEIGHT>, points: u32) {
+        let slot = (0..MAX_POPUPS).min_by_key(|i| self.popups[*i].map_or(0, |popup| popup.until)).unwrap_or(0);
+        self.popups[slot] = Some(Popup {pos, points, until: self.frames + POPUP_FRAMES});
+    }
+
+    // The glyph a live "+N" popup puts on `p`; the text runs rightward
+    // from the cell where the food was eaten.
+    pub fn popup_at(&self, p: Position<WIDTH, HEIGHT>) -> Option<char> {
+        self.popups.iter().flatten()
+            .filter(|popup| self.frames < popup.until && popup.pos.row == p.row && p.col >= popup.pos.col)
+            .find_map(|popup| {
+                let offset = (p.col - popup.pos.col) as u32;
+                let mut digits = 1;
+                while digits < 10 && popup.points / 10u32.pow(digits) > 0 {
+                    digits += 1;
+                }
+                match offset {
+                    0 => Some('+'),
+                    i if i <= digits => {
+                        let digit = popup.points / 10u32.pow(digits - i) % 10;
+                        Some((b'0' + digit as u8) as char)
+                    }
+                    _ => None
+                }
+            })
+    }
+
+    fn maybe_spawn_poison(&mut self) {
+        if self.hazards_frozen {
+            return;
+        }
+        if let Some(chance) = self.difficulty.poison_chance() {
+            if self.positions_of(Cell::Poison).count() < MAX_POISON && self.rng.below(chance) == 0 {
+                if let Some(p) = self.random_empty_cell() {
+                    let (row, col) = p.row_col();
+                    self.cells[row][col] = Cell::Poison;
+                }
+            }
+        }
+    }
+
+    // Costs points and, if there is one, the last body segment.
+    fn eat_poison(&mut self, p: Position<WIDTH, HEIGHT>) {
+        let (row, col) = p.row_col();
+        self.cells[row][col] = Cell::Empty;
+        self.breakdown.base = self.breakdown.base.saturating_sub(POISON_PENALTY);
+        self.points = self.points.saturating_sub(POISON_PENALTY);
+        if let Some(tail) = self.body.pop_back() {
+            let (row, col) = tail.row_col();
+            self.cells[row][col] = Cell::Empty;
+        }
+    }
+
+    fn maybe_spawn_pickups(&mut self) {
+        if self.hazards_frozen {
+            return;
+        }
+        for effect in effects::ALL_EFFECTS.iter() {
+            let pickup = effect.pickup();
+            if self.positions_of(pickup).next().is_none() && self.rng.below(effect.spawn_chance()) == 0 {
+                if let Some(p) = self.random_empty_cell() {
+                    let (row, col) = p.row_col();
+                    self.cells[row][col] = pickup;
+                }
+            }
+        }
+    }
+
+    // Only worth offering once there is a tail long enough to lose.
+    fn maybe_spawn_shrink(&mut self) {
+        if self.hazards_frozen {
+            return;
+        }
+        if self.body.len() > SHRINK_SEGMENTS && self.positions_of(Cell::Shrink).next().is_none()
+            && self.rng.below(SHRINK_CHANCE) == 0 {
+            if let Some(p) = self.random_empty_cell() {
+                let (row, col) = p.row_col();
+                self.cells[row][col] = Cell::Shrink;
+            }
+        }
+    }
+
+    // The head is never part of the body, so length can't drop below 1.
+    fn eat_shrink(&mut self, p: Position<WIDTH, HEIGHT>) {
+        let (row, col) = p.row_col();
+        self.cells[row][col] = Cell::Empty;
+        for _ in 0..SHRINK_SEGMENTS {
+            match self.body.pop_back() {
+                Some(tail) => {
+                    let (row, col) = tail.row_col();
+                    self.cells[row][col] = Cell::Empty;
+                }
+                None => break
+            }
+        }
+    }
+
+    fn slide_movers(&mut self) {
+        self.mover_countdown -= 1;
+        if self.mover_countdown > 0 {
+            return;
+        }
+        self.mover_countdown = MOVER_PERIOD;
+        for i in 0..MAX_MOVERS {
+            if let Some(mover) = self.movers[i] {
+                self.movers[i] = Some(self.slide_mover(mover));
+            }
+        }
+    }
+
+    // A mover pushes into empty cells, crushes the snake on contact and
+    // turns around at anything else, food included.
+    fn slide_mover(&mut self, mut mover: level::Mover<WIDTH, HEIGHT>) -> level::Mover<WIDTH, HEIGHT> {
+        let ahead = mover.lead.neighbor(mover.dir);
+        if !ahead.is_legal() {
+            mover.lead = mover.tail();
+            mover.dir = mover.dir.opposite();
+        } else if ahead == self.snake.pos || self.cell(ahead) == Cell::Body {
+            if self.status == Status::Playing && !self.is_invulnerable() {
+                self.crash();
+            }
+        } else if self.cell(ahead) == Cell::Empty && Some(ahead) != self.rival_head() {
+            let (row, col) = mover.tail().row_col();
+            self.cells[row][col] = Cell::Empty;
+            let (row, col) = ahead.row_col();
+            self.cells[row][col] = Cell::MovingWall;
+            mover.lead = ahead;
+        } else {
+            mover.lead = mover.tail();
+            mover.dir = mover.dir.opposite();
+        }
+        mover
+    }
+
+    pub fn keys_held(&self) -> u32 {
+        self.keys_held
+    }
+
+    // Any key opens every door on the level.
+    fn take_key(&mut self, p: Position<WIDTH, HEIGHT>) {
+        let (row, col) = p.row_col();
+        self.cells[row][col] = Cell::Empty;
+        self.keys_held += 1;
+        for row_cells in self.cells.iter_mut() {
+            for cell in row_cells.iter_mut() {
+                if *cell == Cell::Door {
+                    *cell = Cell::Empty;
+                }
+            }
+        }
+    }
+
+    fn take_pickup(&mut self, p: Position<WIDTH, HEIGHT>, effect: Effect) {
+        let (row, col) = p.row_col();
+        self.cells[row][col] = Cell::Empty;
+        self.effects.grant(effect);
+    }
+
+    // A ghost passes straight through runs of walls, wrapping at the board
+    // edge, and comes out on the first non-wall cell beyond them.
+    fn ghost_exit(&self, dir: Dir) -> Option<Position<WIDTH, HEIGHT>> {
+        let mut p = self.snake.pos;
+        for _ in 0..WIDTH.max(HEIGHT) {
+            p = p.wrapped_neighbor(dir);
+            if self.cell(p) != Cell::Wall {
+                return Some(p);
+            }
+        }
+        None
+    }
+
+    pub fn set_wandering_food(&mut self, wandering_food: bool) {
+        self.wandering_food = wandering_food;
+        self.wanderers = [None; MAX_WANDERERS];
+    }
+
+    pub fn wandering_food(&self) -> bool {
+        self.wandering_food
+    }
+
+    // Freezes every RNG-driven hazard while the snake keeps moving.
+    pub fn set_hazards_frozen(&mut self, hazards_frozen: bool) {
+        self.hazards_frozen = hazards_frozen;
+    }
+
+    pub fn hazards_frozen(&self) -> bool {
+        self.hazards_frozen
+    }
+
+    fn wander_food(&mut self) {
+        self.sync_wanderers();
+        for i in 0..MAX_WANDERERS {
+            if let Some(mut wanderer) = self.wanderers[i] {
+                if wanderer.cooldown > 0 {
+                    wanderer.cooldown -= 1;
+                } else {
+                    let mut open = DirList::new();
+                    for d in ALL_DIRS.iter() {
+                        let n = self.next_pos(wanderer.pos, *d);
+                        if n.is_legal() && self.cell(n) == Cell::Empty && n != self.snake.pos {
+                            open.push(*d);
+                        }
+                    }
+                    if !open.is_empty() {
+                        let dir = open.as_slice()[self.rng.below(open.len())];
+                        let (row, col) = wanderer.pos.row_col();
+                        self.cells[row][col] = Cell::Empty;
+                        wanderer.pos = self.next_pos(wanderer.pos, dir);
+                        let (row, col) = wanderer.pos.row_col();
+                        self.cells[row][col] = Cell::Food;
+                    }
+                    wanderer.cooldown = WANDER_COOLDOWN;
+                }
+                self.wanderers[i] = Some(wanderer);
+            }
+        }
+    }
+
+    // Drops wanderers whose food was eaten or overwritten and adopts any
+    // food that appeared since the last update.
+    fn sync_wanderers(&mut self) {
+        for slot in self.wanderers.iter_mut() {
+            if let Some(wanderer) = *slot {
+                let (row, col) = wanderer.pos.row_col();
+                if self.cells[row][col] != Cell::Food {
+                    *slot = None;
+                }
+            }
+        }
+        for row in 0..HEIGHT {
+            for col in 0..WIDTH {
+                let p = Position::new(row, col);
+                if self.cells[row][col] == Cell::Food && !self.wanderers.iter().any(|w| w.map_or(false, |w| w.pos == p)) {
+                    if let Some(slot) = self.wanderers.iter().position(|w| w.is_none()) {
+                        let cooldown = self.rng.below(WANDER_COOLDOWN as usize + 1) as u32;
+                        self.wanderers[slot] = Some(Wanderer {pos: p, cooldown});
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn arena_inset(&self) -> usize {
+        self.arena_inset
+    }
+
+    fn shrink_interval(&self) -> Option<u64> {
+        self.config.shrink_seconds.map(|seconds| seconds_to_ticks(seconds).max(1))
+    }
+
+    // Walls off the next ring in from the edge. Cells under the snakes or
+    // an enemy stay open, and food caught on the ring respawns inside.
+    fn shrink_arena(&mut self) {
+        if self.hazards_frozen {
+            return;
+        }
+        let ring = self.arena_inset + 1;
+        if HEIGHT < 2 * (ring + MIN_ARENA_RADIUS) || WIDTH < 2 * (ring + MIN_ARENA_RADIUS) {
+            return;
+        }
+        for p in self.cell_pos_iter() {
+            let (row, col) = p.row_col();
+            let on_ring = Self::in_border(row, col, ring + 1) && !Self::in_border(row, col, ring);
+            let open = match self.cell(p) {
+                Cell::Empty | Cell::Food => true,
+                _ => false
+            };
+            if on_ring && open && p != self.snake.pos && Some(p) != self.rival_head() && !self.enemy_at(p) {
+                self.set_cell(p, Cell::Wall);
+            }
+        }
+        self.arena_inset = ring;
+        if self.spawn_delay == 0 {
+            self.top_up_food();
+        }
+    }
+
+    // A block that would cut the snake off from the food is taken back and
+    // another cell tried; the one straight ahead of the head is never used.
+    fn drop_obstacle(&mut self) {
+        if self.hazards_frozen {
+            return;
+        }
+        let ahead = self.next_pos(self.snake.pos, self.snake.dir);
+        for _ in 0..OBSTACLE_TRIES {
+            if let Some(p) = self.random_empty_cell().filter(|p| *p != ahead) {
+                self.set_cell(p, Cell::Wall);
+                if self.food_on_board == 0 || self.food_reachable() {
+                    return;
+                }
+                self.set_cell(p, Cell::Empty);
+            }
+        }
+    }
+
+    fn top_up_food(&mut self) {
+        if self.config.tron {
+            return;
+        }
+        while self.food_on_board < self.config.food_target && self.spawn_food().is_some() {}
+    }
+
+    // Reservoir sampling keeps every empty cell equally likely in one pass.
+    fn random_empty_cell(&mut self) -> Option<Position<WIDTH, HEIGHT>> {
+        let mut chosen = None;
+        let mut seen = 0;
+        for p in self.cell_pos_iter() {
+            let (row, col) = p.row_col();
+            let outside = self.arena_inset > 0 && Self::in_border(row, col, self.arena_inset + 1);
+            if self.cell(p) == Cell::Empty && !outside && p != self.snake.pos && Some(p) != self.rival_head() && !self.enemy_at(p) {
+                seen += 1;
+                if self.rng.below(seen) == 0 {
+                    chosen = Some(p);
+                }
+            }
+        }
+        chosen
+    }
+
+    fn queued_heading(&self) -> Dir {
+        self.inputs.back().unwrap_or(self.snake.dir)
+    }
+
+    fn queue_dir(&mut self, dir: Dir) {
+        let last = self.queued_heading();
+        if dir != last && dir != last.opposite() {
+            self.inputs.push(dir);
+        }
+    }
+
+    pub fn turn_left(&mut self) {
+        self.queue_dir(self.queued_heading().left());
+    }
+
+    pub fn turn_right(&mut self) {
+        self.queue_dir(self.queued_heading().right());
+    }
+
+    fn resolve_move(&mut self) {
+        let dir = if self.playback.is_some() {
+            match self.next_playback_dir() {
+                Some(dir) => dir,
+                None => {
+                    self.stop_playback();
+                    return;
+                }
+            }
+        } else {
+            let (dir, used_input) = self.next_move();
+            if used_input {
+                self.inputs.pop();
+            }
+            dir
+        };
+        if self.playback.is_none() && !self.demo {
+            self.replay.push(dir);
+        }
+        self.step(dir);
+    }
+
+    // The direction the next move takes, and whether it uses up the
+    // queued input. A turn held back by a magnetic wall stays queued.
+    fn next_move(&self) -> (Dir, bool) {
+        match self.inputs.front() {
+            Some(_) if self.active_zone() == Some(ZoneKind::NoTurn) => (self.snake.dir, true),
+            Some(dir) if dir == self.snake.dir.opposite() => (self.snake.dir, true),
+            Some(dir) if self.magnetic_walls && self.turn_into_wall(dir) => (self.snake.dir, false),
+            Some(dir) => (dir, true),
+            None => (self.snake.dir, false)
+        }
+    }
+
+    fn step(&mut self, dir: Dir) {
+        let neighbor = self.next_pos(self.snake.pos, dir);
+        let neighbor = if self.effects.is_active(Effect::Ghost) && (!neighbor.is_legal() || self.cell(neighbor) == Cell::Wall) {
+            self.ghost_exit(dir).unwrap_or(neighbor)
+        } else {
+            neighbor
+        };
+        let neighbor = self.portal_hop(neighbor, dir);
+        if neighbor.is_legal() {
+            // Bedrock, doors and moving walls still stop a wall breaker.
+            if self.effects.is_active(Effect::WallBreaker) {
+                self.break_wall(neighbor);
+            }
+            if self.collides(neighbor) {
+                if !self.is_invulnerable() {
+                    self.crash();
+                }
+            } else {
+                self.move_to(neighbor, dir);
+            }
+        }
+    }
+
+    // Entering a portal comes out of its partner still heading the same
+    // way; the portal cells themselves are never occupied.
+    fn portal_hop(&self, p: Position<WIDTH, HEIGHT>, dir: Dir) -> Position<WIDTH, HEIGHT> {
+        if !p.is_legal() {
+            return p;
+        }
+        match self.cell(p) {
+            Cell::Portal(id) => self.positions_of(Cell::Portal(id))
+                .find(|partner| *partner != p)
+                .map_or(p, |partner| self.next_pos(partner, dir)),
+            _ => p
+        }
+    }
+
+    fn collides(&self, p: Position<WIDTH, HEIGHT>) -> bool {
+        match self.cell(p) {
+            _ if !self.two_player && Some(p) == self.rival_head() => true,
+            Cell::Body => self.next_vacated() != Some(p),
+            Cell::RivalBody => true,
+            cell => cell.is_wall()
+        }
+    }
+
+    // Directions the snake could take next move without crashing.
+    pub fn legal_moves(&self) -> DirList {
+        let mut moves = DirList::new();
+        for d in ALL_DIRS.iter() {
+            let n = self.next_pos(self.snake.pos, *d);
+            if *d != self.snake.dir.opposite() && n.is_legal() && !self.collides(n) {
+                moves.push(*d);
+            }
+        }
+        moves
+    }
+
+    pub fn set_random_turn_on_eat(&mut self, random_turn_on_eat: bool) {
+        self.random_turn_on_eat = random_turn_on_eat;
+    }
+
+    // Going straight on only counts when it is the one legal move left.
+    fn random_turn(&mut self) {
+        let mut turns = DirList::new();
+        for d in self.legal_moves().as_slice() {
+            if *d != self.snake.dir {
+                turns.push(*d);
+            }
+        }
+        if !turns.is_empty() {
+            self.snake.dir = turns.as_slice()[self.rng.below(turns.len())];
+        }
+    }
+
+    fn crash(&mut self) {
+        // Player two still gets their move, so update_rival settles the
+        // round and may yet call it a draw.
+        if self.two_player {
+            self.winner = Some(Winner::PlayerTwo);
+            return;
+        }
+        self.lives = self.lives.saturating_sub(1);
+        if self.lives == 0 {
+            self.end_game(OverCause::Crashed);
+        } else {
+            self.reset_snake_only();
+            self.invulnerable = INVULNERABLE_MOVES;
+            self.feed();
+        }
+    }
+
+    // Ends the run for a death that costs no life. With two players that
+    // hands the round to player two.
+    fn lose_round(&mut self, cause: OverCause) {
+        if self.two_player {
+            self.winner = Some(Winner::PlayerTwo);
+        }
+        self.end_game(cause);
+    }
+
+    fn end_game(&mut self, cause: OverCause) {
+        self.over_cause = cause;
+        self.status = Status::GameOver;
+        self.record_score();
+    }
+
+    // Demo and replay runs are not the player's, so they earn nothing.
+    fn scripted(&self) -> bool {
+        self.demo || self.playback.is_some()
+    }
+
+    fn record_score(&mut self) {
+        if self.scripted() {
+            return;
+        }
+        self.score_rank = high_scores::record_high_score(HighScore {score: self.score(), level: self.current_level + 1});
+        self.save_pending = true;
+    }
+
+    // Disk writes take far too long for an interrupt handler, so a
+    // finished run only queues its save. Whatever runs the game outside
+    // its interrupts takes the save from here and writes it.
+    pub fn take_pending_save(&mut self) -> Option<SaveData> {
+        if core::mem::take(&mut self.save_pending) {
+            Some(self.save_data())
+        } else {
+            None
+        }
+    }
+
+    // Where this run placed in the high score table, if it made it in.
+    pub fn score_rank(&self) -> Option<usize> {
+        self.score_rank
+    }
+
+    fn feed(&mut self) {
+        self.hunger_left = self.config.hunger.unwrap_or(0);
+    }
+
+    fn starve(&mut self) {
+        if self.config.hunger.is_some() && self.status == Status::Playing && !self.is_god_mode() {
+            self.hunger_left = self.hunger_left.saturating_sub(1);
+            if self.hunger_left == 0 {
+                self.lose_round(OverCause::Starved);
+            }
+        }
+    }
+
+    fn turn_into_wall(&self, dir: Dir) -> bool {
+        let turn = self.next_pos(self.snake.pos, dir);
+        let ahead = self.next_pos(self.snake.pos, self.snake.dir);
+        dir != self.snake.dir
+            && turn.is_legal() && self.cell(turn).is_wall()
+            && ahead.is_legal() && !self.cell(ahead).is_wall()
+    }
+
+    fn move_to(&mut self, neighbor: Position<WIDTH, HEIGHT>, dir: Dir) {
+        self.moves += 1;
+        if dir != self.snake.dir {
+            self.turns += 1;
+            self.straight_food = 0;
+        }
+        let vacated = self.advance_body();
+        if self.config.tron {
+            self.breakdown.base += 1;
+            self.points += 1;
+        }
+        self.snake.pos = neighbor;
+        self.snake.dir = dir;
+        match self.cell(neighbor) {
+            Cell::Food => {
+                self.eat(neighbor, vacated);
+                if self.random_turn_on_eat {
+                    self.random_turn();
+                }
+            }
+            Cell::BonusFood => self.eat_bonus(neighbor),
+            Cell::Poison => self.eat_poison(neighbor),
+            Cell::Shrink => self.eat_shrink(neighbor),
+            Cell::Key => self.take_key(neighbor),
+            cell => if let Some(effect) = Effect::from_pickup(cell) {
+                self.take_pickup(neighbor, effect);
+            }
+        }
+    }
+
+    // In tron mode the trail is painted straight onto the board instead of
+    // the ring buffer, so it can outgrow MAX_BODY and never moves.
+    fn advance_body(&mut self) -> Option<Position<WIDTH, HEIGHT>> {
+        let neck = self.snake.pos;
+        if self.config.tron {
+            let (row, col) = neck.row_col();
+            self.cells[row][col] = Cell::Body;
+            None
+        } else if self.pending_growth > 0 && !self.body.is_full() {
+            self.pending_growth -= 1;
+            self.push_body(neck);
+            None
+        } else if let Some(tail) = self.body.pop_back() {
+            let (row, col) = tail.row_col();
+            self.cells[row][col] = Cell::Empty;
+            self.push_body(neck);
+            Some(tail)
+        } else {
+            None
+        }
+    }
+
+    fn push_body(&mut self, p: Position<WIDTH, HEIGHT>) {
+        let (row, col) = p.row_col();
+        self.cells[row][col] = Cell::Body;
+        self.body.push_front(p);
+    }
+
+    pub fn combo(&self) -> u32 {
+        self.combo
+    }
+
+    // Each quick follow-up meal is worth COMBO_POINTS more than the last.
+    fn extend_combo(&mut self) -> u32 {
+        let quick = self.food_eaten > 1 && self.elapsed_ticks - self.last_meal <= COMBO_WINDOW;
+        self.combo = if quick {self.combo + 1} else {0};
+        self.last_meal = self.elapsed_ticks;
+        self.combo * COMBO_POINTS
+    }
+
+    fn eat(&mut self, p: Position<WIDTH, HEIGHT>, vacated: Option<Position<WIDTH, HEIGHT>>) {
+        let (row, col) = p.row_col();
+        self.food_eaten += 1;
+        self.straight_food += 1;
+        self.pending_growth += 1;
+        self.feed();
+        let zone_bonus = match self.active_zone() {
+            Some(ZoneKind::DoublePoints) => FOOD_POINTS,
+            _ => 0
+        };
+        let multiplier_bonus = if self.effects.is_active(Effect::Multiplier) {
+            (FOOD_POINTS + zone_bonus) * (SCORE_MULTIPLIER - 1)
+        } else {
+            0
+        };
+        self.breakdown.base += FOOD_POINTS;
+        self.breakdown.zone_bonus += zone_bonus;
+        let combo_bonus = self.extend_combo();
+        self.breakdown.multiplier_bonus += multiplier_bonus;
+        self.breakdown.combo_bonus += combo_bonus;
+        self.points += FOOD_POINTS + zone_bonus + multiplier_bonus + combo_bonus;
+        self.show_popup(p, FOOD_POINTS + zone_bonus + multiplier_bonus + combo_bonus);
+        self.eaten[self.eaten_count % EATEN_HISTORY] = p;
+        self.eaten_count += 1;
+        if self.has_win_target() && self.food_eaten >= self.config.win_threshold {
+            self.status = Status::Victory;
+            self.record_score();
+        }
+        self.cells[row][col] = Cell::Empty;
+        self.food_on_board -= 1;
+        self.maybe_spawn_bonus();
+        self.maybe_spawn_poison();
+        self.maybe_spawn_pickups();
+        self.maybe_spawn_shrink();
+        match vacated.filter(|_| self.food_at_tail) {
+            Some(tail) => {
+                let (row, col) = tail.row_col();
+                self.cells[row][col] = Cell::Food;
+                self.food_on_board += 1;
+            }
+            None => match self.difficulty.spawn_delay() {
+                0 => self.top_up_food(),
+                delay => self.spawn_delay = delay
+            }
+        }
+        if let Some(every) = self.config.obstacle_every {
+            if every > 0 && self.food_eaten % every == 0 {
+                self.drop_obstacle();
+            }
+        }
+        self.level_food += 1;
+        let goal = self.campaign.stage(self.current_level).map(|stage| stage.food_goal);
+        if self.status == Status::Playing && goal.map_or(false, |goal| self.level_food >= goal) {
+            self.campaign.complete(self.current_level);
+            if self.campaign.is_unlocked(self.current_level + 1) {
+                self.load_level(self.current_level + 1);
+            }
+        }
+    }
+
+    pub fn tail_at(&self) -> Position<WIDTH, HEIGHT> {
+        self.body.tail().unwrap_or(self.snake.pos)
+    }
+
+    pub fn body_iter(&self) -> impl Iterator<Item=Position<WIDTH, HEIGHT>> + '_ {
+        self.body.iter()
+    }
+
+    pub fn next_vacated(&self) -> Option<Position<WIDTH, HEIGHT>> {
+        if self.config.tron || (self.pending_growth > 0 && !self.body.is_full()) {
+            None
+        } else {
+            Some(self.tail_at())
+        }
+    }
+
+    pub fn set_food_at_tail(&mut self, food_at_tail: bool) {
+        self.food_at_tail = food_at_tail;
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn over_cause(&self) -> OverCause {
+        self.over_cause
+    }
+
+    pub fn hunger_left(&self) -> u32 {
+        self.hunger_left
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.status == Status::GameOver || self.status == Status::Victory
+    }
+
+    pub fn campaign(&self) -> &Campaign {
+        &self.campaign
+    }
+
+    // The menu sits on the first stage's board, so picking another stage
+    // loads it before play begins.
+    fn start_selected(&mut self) {
+        if self.campaign.selected() != self.current_level {
+            self.reset();
+            if self.status == Status::Playing {
+                self.status = Status::Menu;
+            }
+        }
+        self.start();
+    }
+
+    pub fn start(&mut self) {
+        if self.status == Status::Menu {
+            self.status = Status::Playing;
+        }
+    }
+
+    pub fn add_zone(&mut self, zone: Zone<WIDTH, HEIGHT>) -> bool {
+        match self.zones.iter_mut().find(|z| z.is_none()) {
+            Some(slot) => {
+                *slot = Some(zone);
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn clear_zones(&mut self) {
+        self.zones = [None; MAX_ZONES];
+    }
+
+    pub fn active_zone(&self) -> Option<ZoneKind> {
+        self.zones.iter()
+            .filter_map(|z| *z)
+            .find(|z| z.contains(self.snake.pos))
+            .map(|z| z.kind)
+    }
+
+    pub fn eaten_positions(&self) -> impl Iterator<Item=Position<WIDTH, HEIGHT>> + '_ {
+        let first = self.eaten_count - self.eaten_count.min(EATEN_HISTORY);
+        (first..self.eaten_count).map(move |i| self.eaten[i % EATEN_HISTORY])
+    }
+
+    fn key2dir(key: DecodedKey) -> Option<Dir> {
+        match key {
             DecodedKey::RawKey(k) => match k {
                 KeyCode::ArrowUp => Some(Dir::N),
                 KeyCode::ArrowDown => Some(Dir::S),
@@ -460,3 +3144,1110 @@ impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH, HEIGHT> {
 
 
 
+
+fn wasd_dir(c: char) -> Option<Dir> {
+    match c {
+        'w' => Some(Dir::N),
+        'a' => Some(Dir::W),
+        's' => Some(Dir::S),
+        'd' => Some(Dir::E),
+        _ => None
+    }
+}
+
+fn seconds_to_ticks(seconds: u32) -> u64 {
+    seconds as u64 * TICKS_PER_MINUTE / 60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::GridRenderer;
+
+    type Small = SnakeGame<10,10>;
+
+    const SEED: u32 = 1;
+
+    const WRAP_ROW: &str =
+        "##########
+         #E       #
+         #        #
+         #        #
+         # <***   #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    const BOX: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         #   <    #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    const CORNER: &str =
+        "##########
+         #<       #
+         #        #
+         #        #
+         #        #
+         #        #
+         #        #
+         #        #
+         #       *#
+         ##########";
+
+    fn game_with(map: &'static str, config: GameConfig) -> Small {
+        let mut game = Small::from_source(MapSource::Text(map), GameConfig {seed: Some(SEED), ..config}, &[]);
+        game.start();
+        game
+    }
+
+    // Only the food drawn on the map, and nothing random turning up.
+    fn playing(map: &'static str) -> Small {
+        let mut game = game_with(map, GameConfig {food_target: 0, ..GameConfig::default()});
+        game.set_hazards_frozen(true);
+        game
+    }
+
+    #[test]
+    fn frozen_hazards_stay_put_while_the_snake_moves() {
+        let mut game = game_with(WRAP_ROW, GameConfig {
+            food_target: 0,
+            board_mode: BoardMode::Wrapping,
+            obstacle_every: Some(1),
+            shrink_seconds: Some(1),
+            ..GameConfig::default()
+        });
+        game.set_hazards_frozen(true);
+        let enemy = game.enemy_positions().next();
+        for _ in 0..40 {
+            game.advance_frame();
+            game.update();
+        }
+        assert_eq!(game.status(), Status::Playing);
+        assert_eq!(game.moves, 40);
+        assert_eq!(game.food_eaten, 3);
+        assert_eq!(game.count_cells(Cell::Wall), 0);
+        assert_eq!(game.arena_inset(), 0);
+        for cell in [Cell::BonusFood, Cell::Poison, Cell::Ghost, Cell::SlowMotion, Cell::Multiplier, Cell::WallBreaker, Cell::Shrink].iter() {
+            assert_eq!(game.count_cells(*cell), 0);
+        }
+        assert_eq!(game.enemy_positions().next(), enemy);
+    }
+
+    #[test]
+    fn thicker_border_leaves_a_smaller_interior() {
+        let mut game = playing(BOX);
+        assert!(game.thicken_border(2));
+        let open: Vec<_> = game.cell_pos_iter().filter(|p| !game.cell(*p).is_wall()).collect();
+        assert_eq!(open.len(), 36);
+        assert!(open.iter().all(|p| {
+            let (row, col) = p.row_col();
+            (2..8).contains(&row) && (2..8).contains(&col)
+        }));
+    }
+
+    #[test]
+    fn border_that_would_bury_the_snake_is_refused() {
+        let mut game = playing(CORNER);
+        let before = game.cells;
+        assert!(!game.thicken_border(2));
+        assert_eq!(game.cells, before);
+    }
+
+    #[test]
+    fn border_over_the_body_is_refused_even_with_the_head_clear() {
+        let mut game = playing(FOOD_ROW);
+        run(&mut game, 4);
+        game.queue_dir(Dir::S);
+        game.update();
+        game.queue_dir(Dir::W);
+        game.update();
+        assert_eq!(game.snake_at(), Position::new(5, 5));
+        assert_eq!(game.cell(Position::new(5, 6)), Cell::Body);
+        let before = game.cells;
+        assert!(!game.thicken_border(4));
+        assert_eq!(game.cells, before);
+        assert!(game.thicken_border(3));
+    }
+
+    const FOOD_ROW: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         # < **   #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    fn run<const WIDTH: usize, const HEIGHT: usize>(game: &mut SnakeGame<WIDTH,HEIGHT>, updates: usize) {
+        for _ in 0..updates {
+            game.update();
+        }
+    }
+
+    #[test]
+    fn snake_only_reset_keeps_the_board() {
+        let mut game = playing(FOOD_ROW);
+        run(&mut game, 4);
+        assert_eq!(game.food_eaten, 2);
+        assert_eq!(game.length(), 3);
+        game.reset_snake_only();
+        assert_eq!(game.snake_at(), Position::new(4, 2));
+        assert_eq!(game.heading(), Dir::E);
+        assert_eq!(game.length(), 1);
+        assert_eq!(game.cell(Position::new(4, 4)), Cell::Empty);
+        assert_eq!(game.cell(Position::new(4, 5)), Cell::Empty);
+        assert_eq!(game.food_count(), 0);
+        assert_eq!(game.food_eaten, 2);
+    }
+
+    const LEDGE: &str =
+        "##########
+         #### #####
+         #<       #
+         #        #
+         #        #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn magnetic_walls_hold_a_turn_until_it_opens_up() {
+        let mut game = playing(LEDGE);
+        game.set_magnetic_walls(true);
+        game.queue_dir(Dir::N);
+        run(&mut game, 3);
+        assert_eq!(game.snake_at(), Position::new(2, 4));
+        assert_eq!(game.heading(), Dir::N);
+        game.update();
+        assert_eq!(game.snake_at(), Position::new(1, 4));
+        assert_eq!(game.lives(), 3);
+    }
+
+    #[test]
+    fn without_magnetic_walls_the_turn_crashes() {
+        let mut game = playing(LEDGE);
+        game.queue_dir(Dir::N);
+        game.update();
+        assert_eq!(game.lives(), 2);
+    }
+
+    #[test]
+    fn about_to_eat_only_just_before_a_meal() {
+        let mut game = playing(FOOD_ROW);
+        assert!(!game.about_to_eat());
+        game.update();
+        assert!(game.about_to_eat());
+        game.update();
+        assert_eq!(game.food_eaten, 1);
+        assert!(game.about_to_eat());
+        game.update();
+        assert_eq!(game.food_eaten, 2);
+        assert!(!game.about_to_eat());
+    }
+
+    #[test]
+    fn about_to_eat_ignores_a_turn_the_snake_cannot_take() {
+        let mut game = playing(FOOD_ROW);
+        assert!(game.add_zone(Zone::new(Position::new(4, 2), 3, 1, ZoneKind::NoTurn)));
+        game.update();
+        game.queue_dir(Dir::N);
+        assert_eq!(game.cell_ahead(), Cell::Empty);
+        assert!(game.about_to_eat());
+        game.update();
+        assert_eq!(game.food_eaten, 1);
+        assert_eq!(game.heading(), Dir::E);
+    }
+
+    #[test]
+    fn exponential_ramp_outpaces_linear() {
+        let mut game = playing(BOX);
+        game.set_base_frequency(8);
+        game.food_eaten = 10;
+        game.set_ramp(RampKind::Linear);
+        let linear = game.update_frequency();
+        game.set_ramp(RampKind::Exponential);
+        let exponential = game.update_frequency();
+        assert_eq!(linear, 7);
+        assert_eq!(exponential, 2);
+        game.set_ramp(RampKind::Flat);
+        assert_eq!(game.update_frequency(), 8);
+    }
+
+    const SEALED_FOOD: &str =
+        "##########
+         #      o*#
+         #       o#
+         #        #
+         #   <    #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn food_behind_the_body_is_unreachable() {
+        assert!(playing(CORNER).food_reachable());
+        assert!(!playing(SEALED_FOOD).food_reachable());
+    }
+
+    const THREE_FOOD: &str =
+        "##########
+         #        #
+         # *   *  #
+         #        #
+         #        #
+         #   *    #
+         #        #
+         #<       #
+         #        #
+         ##########";
+
+    #[test]
+    fn centroid_averages_the_food() {
+        assert_eq!(playing(THREE_FOOD).food_centroid(), Some(Position::new(3, 4)));
+        assert_eq!(playing(BOX).food_centroid(), None);
+    }
+
+    fn main_game() -> MainGame {
+        let mut game: MainGame = SnakeGame::new(GameConfig {seed: Some(SEED), ..GameConfig::default()});
+        game.set_hazards_frozen(true);
+        game.start();
+        game
+    }
+
+    #[test]
+    fn board_is_drawn_below_the_header() {
+        let game = main_game();
+        let mut grid = GridRenderer::new();
+        draw(&mut grid, &game);
+        assert!(grid.text(SCORE_ROW).starts_with("Score: 0"));
+        assert!(grid.text(SCORE_ROW)[LEVEL_COL..].starts_with("Level: 1"));
+        assert_eq!(grid.cells[HEADER_SPACE][0], ('#', ColorCode::new(Color::Blue, Color::Black)));
+        assert_eq!(grid.cells[HEADER_SPACE + 10][40], ('<', ColorCode::new(SNAKE_COLOR, Color::Black)));
+        assert_eq!(grid.cells[HEADER_SPACE + 16][19], ('.', ColorCode::new(Color::White, Color::Black)));
+        assert_eq!(grid.cells[BUFFER_HEIGHT - 1][0].0, '#');
+    }
+
+    #[test]
+    fn vacated_tail_is_redrawn_in_the_board_color() {
+        let mut game = main_game();
+        game.set_board_color(Color::Blue);
+        game.pending_growth = 2;
+        let mut grid = GridRenderer::new();
+        run(&mut game, 2);
+        draw(&mut grid, &game);
+        assert_eq!(grid.cells[HEADER_SPACE + 10][40], ('o', ColorCode::new(SNAKE_COLOR, Color::Blue)));
+        run(&mut game, 1);
+        draw(&mut grid, &game);
+        assert_eq!(grid.cells[HEADER_SPACE + 10][40], (' ', ColorCode::new(Color::Blue, Color::Blue)));
+        assert_eq!(grid.cells[HEADER_SPACE][0], ('#', ColorCode::new(Color::Blue, Color::Blue)));
+    }
+
+    const NOOK: &str =
+        "##########
+         #*<      #
+         #        #
+         #        #
+         #        #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn open_neighbors_skip_walls_and_food() {
+        let game = playing(NOOK);
+        assert_eq!(game.open_neighbors().as_slice(), &[Dir::E, Dir::S]);
+    }
+
+    #[test]
+    fn background_tiles_only_show_on_empty_cells() {
+        let mut game = main_game();
+        game.set_background(&vec![":".repeat(41); 11].join("\n"));
+        let icon = |p: Position<BUFFER_WIDTH,GAME_HEIGHT>| get_icon_color(&game, p, &game.cell(p)).0;
+        assert_eq!(icon(Position::new(1, 1)), ':');
+        assert_eq!(icon(Position::new(0, 0)), '#');
+        assert_eq!(icon(Position::new(10, 40)), '<');
+        assert_eq!(icon(Position::new(1, 41)), ' ');
+    }
+
+    #[test]
+    fn relative_turns_from_east() {
+        let mut game = playing(BOX);
+        game.turn_left();
+        assert_eq!(game.heading(), Dir::N);
+        let mut game = playing(BOX);
+        game.turn_right();
+        assert_eq!(game.heading(), Dir::S);
+        game.update();
+        assert_eq!(game.snake_at(), Position::new(5, 4));
+    }
+
+    #[test]
+    fn pedal_speeds_up_only_while_held() {
+        let mut game = playing(BOX);
+        game.set_pedal_mode(true);
+        let idle = game.update_frequency();
+        game.press_pedal();
+        assert!(game.pedal_active());
+        assert!(game.update_frequency() < idle);
+        game.release_pedal();
+        assert!(!game.pedal_active());
+        assert_eq!(game.update_frequency(), idle);
+    }
+
+    #[test]
+    fn occupancy_mask_marks_walls_and_snake() {
+        let mut game = playing(FOOD_ROW);
+        run(&mut game, 4);
+        let mut mask = [0u64; 2];
+        game.occupancy_mask(&mut mask);
+        let set = |row: usize, col: usize| mask[(row * 10 + col) / 64] & 1 << ((row * 10 + col) % 64) != 0;
+        for i in 0..10 {
+            assert!(set(0, i) && set(9, i) && set(i, 0) && set(i, 9));
+        }
+        assert!(set(4, 4) && set(4, 5) && set(4, 6));
+        assert!(!set(4, 3) && !set(4, 7) && !set(5, 5));
+    }
+
+    const THREE_IN_A_ROW: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         # <***   #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn speedup_countdown_after_three_meals() {
+        let mut game = playing(THREE_IN_A_ROW);
+        assert_eq!(game.food_until_speedup(), 5);
+        run(&mut game, 4);
+        assert_eq!(game.food_eaten, 3);
+        assert_eq!(game.food_until_speedup(), 2);
+        game.set_ramp(RampKind::Flat);
+        assert_eq!(game.food_until_speedup(), 0);
+    }
+
+    const SPLIT: &str =
+        "##########
+         #     #  #
+         #     #  #
+         #     #  #
+         #  <  #  #
+         #     #  #
+         #     #  #
+         #     #  #
+         #     # *#
+         ##########";
+
+    #[test]
+    fn largest_open_rect_finds_the_wider_room() {
+        assert_eq!(playing(SPLIT).largest_open_rect(), (Position::new(1, 1), 5, 8));
+        assert_eq!(playing(BOX).largest_open_rect(), (Position::new(1, 1), 8, 8));
+    }
+
+    #[test]
+    fn hud_runs_once_per_drawn_tick() {
+        let mut game = main_game();
+        let mut grid = GridRenderer::new();
+        let mut calls = 0;
+        for _ in 0..12 {
+            hud_tick(&mut game, &mut |_| calls += 1, |game| tick_with_renderer(&mut grid, game));
+        }
+        assert_eq!(calls, 3);
+        assert_eq!(game.moves, 3);
+        let mut menu: MainGame = SnakeGame::new(GameConfig::default());
+        hud_tick(&mut menu, &mut |_| calls += 1, |game| tick_with_renderer(&mut grid, game));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn projected_path_stops_at_the_wall() {
+        let game = playing(BOX);
+        let mut path = [Position::new(0, 0); 10];
+        assert_eq!(game.project_path(2, &mut path), 2);
+        assert_eq!(game.project_path(10, &mut path), 4);
+        let expected: Vec<_> = (5..9).map(|col| Position::new(4, col)).collect();
+        assert_eq!(&path[..4], &expected[..]);
+    }
+
+    #[test]
+    fn board_starts_below_a_taller_header() {
+        let game = main_game();
+        let mut grid = GridRenderer::new();
+        draw_board_at(&mut grid, &game, 3);
+        for row in 0..3 {
+            assert_eq!(grid.text(row).trim(), "");
+        }
+        assert_eq!(grid.text(3), "#".repeat(BUFFER_WIDTH));
+        assert_eq!(HEADER_SPACE + GAME_HEIGHT, BUFFER_HEIGHT);
+    }
+
+    #[test]
+    fn fill_fraction_counts_the_snake_over_open_cells() {
+        let mut game = playing(THREE_IN_A_ROW);
+        assert_eq!(game.fill_fraction(), (1, 64));
+        run(&mut game, 5);
+        assert_eq!(game.fill_fraction(), (4, 64));
+    }
+
+    #[test]
+    fn eaten_positions_in_order() {
+        let mut game = playing(FOOD_ROW);
+        run(&mut game, 4);
+        let eaten: Vec<_> = game.eaten_positions().collect();
+        assert_eq!(eaten, [Position::new(4, 4), Position::new(4, 5)]);
+    }
+
+    #[test]
+    fn slow_zone_only_slows_inside_it() {
+        let mut game = playing(BOX);
+        assert!(game.add_zone(Zone::new(Position::new(4, 5), 2, 1, ZoneKind::Slow)));
+        let normal = game.update_frequency();
+        game.update();
+        assert_eq!(game.active_zone(), Some(ZoneKind::Slow));
+        assert_eq!(game.update_frequency(), normal * ZONE_SLOWDOWN);
+        run(&mut game, 2);
+        assert_eq!(game.active_zone(), None);
+        assert_eq!(game.update_frequency(), normal);
+    }
+
+    const WALL_ROW: [char; 10] = ['#'; 10];
+    const OPEN_ROW: [char; 10] = ['#', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', '#'];
+    const CORNER_GRID: [[char; 10]; 10] = [
+        WALL_ROW,
+        ['#', '<', ' ', ' ', ' ', ' ', ' ', ' ', ' ', '#'],
+        OPEN_ROW, OPEN_ROW, OPEN_ROW, OPEN_ROW, OPEN_ROW, OPEN_ROW,
+        ['#', ' ', ' ', ' ', ' ', ' ', ' ', ' ', '*', '#'],
+        WALL_ROW,
+    ];
+
+    #[test]
+    fn char_grid_matches_the_same_map_as_text() {
+        let from_grid = Small::from_chars(&CORNER_GRID);
+        let from_text = Small::with_map(CORNER);
+        assert_eq!(from_grid.status(), Status::Menu);
+        assert_eq!(from_grid.cells, from_text.cells);
+        assert_eq!(from_grid.snake_at(), from_text.snake_at());
+        assert_eq!(from_grid.heading(), from_text.heading());
+    }
+
+    #[test]
+    fn auto_restart_after_the_delay() {
+        let mut game = game_with(BOX, GameConfig {lives: 1, food_target: 0, ..GameConfig::default()});
+        game.set_auto_restart(Some(5));
+        run(&mut game, 5);
+        assert_eq!(game.status(), Status::GameOver);
+        for _ in 0..4 {
+            game.advance_frame();
+        }
+        assert_eq!(game.status(), Status::GameOver);
+        game.advance_frame();
+        assert_eq!(game.status(), Status::Playing);
+        assert_eq!(game.snake_at(), Position::new(4, 4));
+    }
+
+    #[test]
+    fn score_rate_per_minute() {
+        let mut game = playing(BOX);
+        game.points = 10;
+        assert_eq!(game.score_rate(), 0);
+        game.elapsed_ticks = 2 * TICKS_PER_MINUTE;
+        assert_eq!(game.score_rate(), 5);
+    }
+
+    const BEDROCK: &str =
+        "XXXXXXXXXX
+         X        X
+         X #X     X
+         X        X
+         X   <    X
+         X        X
+         X        X
+         X        X
+         X       *X
+         XXXXXXXXXX";
+
+    #[test]
+    fn bedrock_cannot_be_broken() {
+        let mut game = playing(BEDROCK);
+        assert!(game.break_wall(Position::new(2, 2)));
+        assert_eq!(game.cell(Position::new(2, 2)), Cell::Empty);
+        assert!(!game.break_wall(Position::new(2, 3)));
+        assert!(!game.set_cell(Position::new(0, 0), Cell::Empty));
+        assert_eq!(game.cell(Position::new(2, 3)), Cell::Bedrock);
+        assert_eq!(game.cell(Position::new(0, 0)), Cell::Bedrock);
+    }
+
+    const INNER_WALL: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         #   <  # #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn wall_breaker_pickup_smashes_through_a_wall() {
+        let mut game = playing(INNER_WALL);
+        game.set_cell(Position::new(4, 5), Cell::WallBreaker);
+        run(&mut game, 3);
+        assert!(game.effects.is_active(Effect::WallBreaker));
+        assert_eq!(game.status(), Status::Playing);
+        assert_eq!(game.snake_at(), Position::new(4, 7));
+        assert_eq!(game.cell(Position::new(4, 7)), Cell::Empty);
+        assert_eq!(game.lives, GameConfig::default().lives);
+    }
+
+    #[test]
+    fn without_the_wall_breaker_the_wall_stops_the_snake() {
+        let mut game = playing(INNER_WALL);
+        run(&mut game, 3);
+        assert_eq!(game.cell(Position::new(4, 7)), Cell::Wall);
+        assert_eq!(game.lives, GameConfig::default().lives - 1);
+    }
+
+    #[test]
+    fn head_color_follows_effect_precedence() {
+        let mut game = playing(BOX);
+        assert_eq!(game.effective_head_color(), SNAKE_COLOR);
+        game.grant_effect(Effect::SlowMotion);
+        game.grant_effect(Effect::Multiplier);
+        assert_eq!(game.effective_head_color(), SNAKE_COLOR);
+        game.rainbow = true;
+        assert_eq!(game.effective_head_color(), game.rainbow_color(game.snake_at()));
+        game.grant_effect(Effect::Ghost);
+        assert_eq!(game.effective_head_color(), GHOST_COLOR);
+        game.invulnerable = INVULNERABLE_MOVES;
+        for open in [false, true] {
+            game.snake.open = open;
+            assert_eq!(game.effective_head_color(), Color::DarkGray);
+        }
+        game.invulnerable = 0;
+        assert_eq!(game.effective_head_color(), GHOST_COLOR);
+    }
+
+    #[test]
+    fn toast_clears_after_its_frames() {
+        let mut game = playing(BOX);
+        game.show_toast("Hello", 3);
+        for _ in 0..2 {
+            assert_eq!(game.toast(), Some("Hello"));
+            game.advance_frame();
+        }
+        assert_eq!(game.toast(), Some("Hello"));
+        game.advance_frame();
+        assert_eq!(game.toast(), None);
+        game.show_toast(&"x".repeat(TOAST_LEN + 5), 1);
+        assert_eq!(game.toast().map(str::len), Some(TOAST_LEN));
+    }
+
+    #[test]
+    fn dir_safety_looks_ahead_for_walls() {
+        let game = playing(BOX);
+        assert!(game.is_dir_safe(Dir::E, 4));
+        assert!(!game.is_dir_safe(Dir::E, 5));
+        assert!(game.is_dir_safe(Dir::N, 3));
+        assert!(!game.is_dir_safe(Dir::N, 4));
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn stress_board_is_nearly_full_and_playable() {
+        let mut game = MainGame::stress();
+        assert_eq!(game.status(), Status::Playing);
+        assert!(game.empty_cell_count() <= BUFFER_WIDTH);
+        assert!(game.food_count() > 0);
+        assert_eq!(game.food_count(), game.count_cells(Cell::Food));
+        let mut grid = GridRenderer::new();
+        game.update();
+        draw(&mut grid, &game);
+    }
+
+    #[test]
+    fn positions_of_start_map_walls_and_food() {
+        let game = main_game();
+        let walls: Vec<_> = game.positions_of(Cell::Wall).collect();
+        assert_eq!(walls.len(), 2 * BUFFER_WIDTH + 2 * (GAME_HEIGHT - 2));
+        assert!(walls.iter().all(|p| {
+            let (row, col) = p.row_col();
+            MainGame::in_border(row, col, 1)
+        }));
+        let food: Vec<_> = game.positions_of(Cell::Food).collect();
+        assert_eq!(food, [Position::new(16, 19)]);
+    }
+
+    #[test]
+    fn breakdown_splits_base_and_combo_points() {
+        let mut game = playing(THREE_IN_A_ROW);
+        run(&mut game, 4);
+        assert_eq!(game.combo(), 2);
+        assert_eq!(game.score_breakdown(), ScoreBreakdown {base: 3, zone_bonus: 0, multiplier_bonus: 0, combo_bonus: 3});
+        assert_eq!(game.score(), 6);
+    }
+
+    #[test]
+    fn half_rotation_flips_input_and_board() {
+        let mut game = playing(BOX);
+        game.set_screen_rotation(Rotation::Half);
+        game.key(DecodedKey::RawKey(KeyCode::ArrowUp));
+        game.update();
+        assert_eq!(game.snake_at(), Position::new(5, 4));
+
+        let mut game = main_game();
+        game.set_screen_rotation(Rotation::Half);
+        let mut grid = GridRenderer::new();
+        draw(&mut grid, &game);
+        assert_eq!(grid.cells[HEADER_SPACE + GAME_HEIGHT - 1 - 10][BUFFER_WIDTH - 1 - 40].0, '<');
+        assert_eq!(grid.cells[HEADER_SPACE + GAME_HEIGHT - 1 - 16][BUFFER_WIDTH - 1 - 19].0, '.');
+    }
+
+    fn graded(food: u32, moves: u32, turns: u32, ticks: u64) -> char {
+        let mut game = playing(BOX);
+        game.food_eaten = food;
+        game.points = food;
+        game.moves = moves;
+        game.turns = turns;
+        game.elapsed_ticks = ticks;
+        game.grade()
+    }
+
+    #[test]
+    fn efficient_runs_grade_higher() {
+        assert_eq!(graded(30, 100, 30, 3000), 'S');
+        assert_eq!(graded(10, 300, 30, 3000), 'B');
+        assert_eq!(graded(5, 500, 50, 10_000), 'D');
+        assert_eq!(playing(BOX).grade(), 'D');
+    }
+
+    // Eats the row's three food and curls round so the next move north
+    // lands on the cell the tail is about to leave.
+    fn curled(lives: u32) -> Small {
+        let mut game = game_with(THREE_IN_A_ROW, GameConfig {lives, food_target: 0, ..GameConfig::default()});
+        game.set_hazards_frozen(true);
+        run(&mut game, 5);
+        game.queue_dir(Dir::S);
+        game.queue_dir(Dir::W);
+        run(&mut game, 2);
+        assert_eq!(game.length(), 4);
+        assert_eq!(game.next_vacated(), Some(Position::new(4, 6)));
+        game.queue_dir(Dir::N);
+        game
+    }
+
+    #[test]
+    fn head_may_chase_its_own_tail() {
+        let mut game = curled(1);
+        game.update();
+        assert_eq!(game.status(), Status::Playing);
+        assert_eq!(game.snake_at(), Position::new(4, 6));
+        assert_eq!(game.tail_at(), Position::new(4, 7));
+    }
+
+    #[test]
+    fn running_into_the_body_ends_the_game() {
+        let mut game = curled(1);
+        game.pending_growth = 1;
+        assert_eq!(game.next_vacated(), None);
+        game.update();
+        assert_eq!(game.status(), Status::GameOver);
+        assert_eq!(game.over_cause(), OverCause::Crashed);
+    }
+
+    const PACKED: &str =
+        "##########
+         #oooooooo#
+         #oooooooo#
+         #oooooooo#
+         #ooo<oooo#
+         #oooooooo#
+         #oooooooo#
+         #oooooooo#
+         #ooooooo #
+         ##########";
+
+    #[test]
+    fn food_takes_the_only_empty_cell() {
+        let mut game = playing(PACKED);
+        assert_eq!(game.spawn_food(), Some(Position::new(8, 8)));
+        assert_eq!(game.spawn_food(), None);
+        assert_eq!(game.count_cells(Cell::Body), 62);
+    }
+
+    #[test]
+    fn food_never_covers_walls_or_the_head() {
+        let mut game = playing(BOX);
+        for _ in 0..63 {
+            assert!(game.spawn_food().is_some());
+        }
+        assert_eq!(game.spawn_food(), None);
+        assert_eq!(game.count_cells(Cell::Wall), 36);
+        assert_eq!(game.food_count(), 63);
+        assert_eq!(game.cell(game.snake_at()), Cell::Empty);
+    }
+
+    const TWO_STAGES: [Stage; 2] = [
+        Stage {map: FOOD_ROW, food_goal: 2},
+        Stage {map: BOX, food_goal: 5},
+    ];
+
+    #[test]
+    fn food_target_holds_through_meals_levels_and_resets() {
+        let config = GameConfig {seed: Some(SEED), food_target: 3, ..GameConfig::default()};
+        let mut game = Small::from_source(MapSource::Text(FOOD_ROW), config, &TWO_STAGES);
+        game.set_hazards_frozen(true);
+        game.start();
+        let on_board = |game: &Small| (game.food_count(), game.count_cells(Cell::Food));
+        assert_eq!(on_board(&game), (3, 3));
+        for _ in 0..3 {
+            game.update();
+            assert_eq!(on_board(&game), (3, 3));
+        }
+        assert_eq!(game.current_level(), 1);
+        game.reset();
+        assert_eq!(game.current_level(), 0);
+        assert_eq!(on_board(&game), (3, 3));
+    }
+
+    const GAPPED_FOOD: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         # <** *  #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn combo_needs_meals_inside_the_window() {
+        let mut game = playing(GAPPED_FOOD);
+        game.update();
+        assert_eq!(game.combo(), 0);
+        game.elapsed_ticks += COMBO_WINDOW;
+        game.update();
+        assert_eq!(game.combo(), 1);
+        assert_eq!(game.score(), 3);
+        game.elapsed_ticks += COMBO_WINDOW + 1;
+        game.update();
+        assert_eq!(game.combo(), 0);
+        game.update();
+        assert_eq!(game.combo(), 0);
+        assert_eq!(game.score(), 4);
+        assert_eq!(game.score_breakdown().combo_bonus, 1);
+    }
+
+    // Player two starts on the mirrored cell, (5,5), heading East.
+    const P1_FACES_WALL: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         #  #>    #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    const P2_FACES_WALL: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         #   >    #
+         #     #  #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    const BOTH_FACE_WALLS: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         #  #>    #
+         #     #  #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    fn first_move_winner(map: &'static str) -> Option<Winner> {
+        let mut game = playing(map);
+        game.set_two_player(true);
+        game.update();
+        assert_eq!(game.status(), Status::GameOver);
+        game.winner()
+    }
+
+    #[test]
+    fn crashes_on_the_same_move_are_a_draw() {
+        assert_eq!(first_move_winner(P1_FACES_WALL), Some(Winner::PlayerTwo));
+        assert_eq!(first_move_winner(P2_FACES_WALL), Some(Winner::PlayerOne));
+        assert_eq!(first_move_winner(BOTH_FACE_WALLS), Some(Winner::Draw));
+    }
+
+    #[test]
+    fn two_player_runs_cannot_be_replayed() {
+        let mut game = playing(P2_FACES_WALL);
+        game.set_two_player(true);
+        game.update();
+        assert!(game.replay_len() > 0);
+        assert!(!game.start_playback());
+        assert!(!game.is_playback());
+    }
+
+    #[test]
+    fn a_finished_run_queues_its_save_for_later() {
+        let mut game = game_with(P1_FACES_WALL, GameConfig {lives: 1, food_target: 0, ..GameConfig::default()});
+        game.update();
+        assert_eq!(game.status(), Status::GameOver);
+        assert!(game.save_pending);
+        assert!(game.take_pending_save().is_some());
+        assert!(!game.save_pending);
+        assert!(game.take_pending_save().is_none());
+    }
+
+    const TIMED: &str =
+        "; Timed | 0 | 1
+         ##########
+         #        #
+         #        #
+         #        #
+         #   <*   #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    const TIMED_STAGES: [Stage; 2] = [
+        Stage {map: TIMED, food_goal: 1},
+        Stage {map: TIMED, food_goal: 1},
+    ];
+
+    #[test]
+    fn running_out_of_level_time_ends_the_game() {
+        let mut game = playing(TIMED);
+        let budget = seconds_to_ticks(1);
+        assert_eq!(game.level_time_remaining(), Some(budget));
+        for _ in 1..budget {
+            game.advance_frame();
+        }
+        assert_eq!(game.level_time_remaining(), Some(1));
+        assert_eq!(game.status(), Status::Playing);
+        game.advance_frame();
+        assert_eq!(game.status(), Status::GameOver);
+        assert_eq!(game.over_cause(), OverCause::TimeUp);
+    }
+
+    #[test]
+    fn a_new_level_gets_a_fresh_time_budget() {
+        let config = GameConfig {seed: Some(SEED), food_target: 0, ..GameConfig::default()};
+        let mut game = Small::from_source(MapSource::Text(TIMED), config, &TIMED_STAGES);
+        game.set_hazards_frozen(true);
+        game.start();
+        let budget = seconds_to_ticks(1);
+        for _ in 0..5 {
+            game.advance_frame();
+        }
+        assert_eq!(game.level_time_remaining(), Some(budget - 5));
+        game.update();
+        assert_eq!(game.current_level(), 1);
+        assert_eq!(game.level_time_remaining(), Some(budget));
+    }
+
+    const FOOD_ALL_ROUND: &str =
+        "##########
+         #        #
+         #   *    #
+         #        #
+         # * < *  #
+         #        #
+         #   *    #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn equidistant_food_goes_to_the_lowest_row_then_column() {
+        let mut game = playing(FOOD_ALL_ROUND);
+        let from = Position::new(4, 4);
+        assert_eq!(game.nearest_food(from), Some(Position::new(2, 4)));
+        assert!(game.set_cell(Position::new(2, 4), Cell::Empty));
+        assert_eq!(game.nearest_food(from), Some(Position::new(4, 2)));
+    }
+
+    #[test]
+    fn a_lost_life_shows_as_an_empty_heart() {
+        let mut game = playing(P1_FACES_WALL);
+        game.update();
+        assert_eq!(game.lives(), 2);
+        let mut hearts = [' '; 3];
+        assert_eq!(game.hearts(&mut hearts), 3);
+        assert_eq!(hearts, [HEART, HEART, EMPTY_HEART]);
+    }
+
+    fn heading_after_a_meal(map: &'static str) -> Dir {
+        let mut game = playing(map);
+        game.set_random_turn_on_eat(true);
+        game.update();
+        assert_eq!(game.food_eaten, 1);
+        assert!(!game.legal_moves().is_empty());
+        game.snake.dir
+    }
+
+    #[test]
+    fn eating_forces_a_legal_repeatable_turn() {
+        let dir = heading_after_a_meal(THREE_IN_A_ROW);
+        assert!(dir == Dir::N || dir == Dir::S);
+        assert_eq!(heading_after_a_meal(THREE_IN_A_ROW), dir);
+        assert_eq!(heading_after_a_meal(SHAFT), Dir::N);
+    }
+
+    // Eating at the top of the shaft leaves straight on as the only way
+    // out.
+    const SHAFT: &str =
+        "##########
+         #### #####
+         ####*#####
+         ####v#####
+         #        #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn paused_ticks_only_count_while_paused() {
+        let mut game = playing(BOX);
+        game.toggle_pause();
+        for _ in 0..5 {
+            game.advance_frame();
+        }
+        assert_eq!(game.paused_ticks(), 5);
+        assert_eq!(game.elapsed_ticks(), 0);
+        game.toggle_pause();
+        for _ in 0..3 {
+            game.advance_frame();
+        }
+        assert_eq!(game.paused_ticks(), 5);
+        assert_eq!(game.elapsed_ticks(), 3);
+    }
+
+    const TAIL_MEAL: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         #<*  *   #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    // The first meal leaves no tail behind; by the second the snake has
+    // stopped growing and its tail leaves (4,3).
+    fn second_meal(food_at_tail: bool) -> Small {
+        let mut game = playing(TAIL_MEAL);
+        game.set_food_at_tail(food_at_tail);
+        game.update();
+        assert_eq!(game.food_eaten, 1);
+        assert_eq!(game.food_count(), 1);
+        run(&mut game, 2);
+        assert_eq!(game.next_vacated(), Some(Position::new(4, 3)));
+        game.update();
+        assert_eq!(game.food_eaten, 2);
+        game
+    }
+
+    #[test]
+    fn food_at_tail_lands_where_the_tail_left() {
+        let game = second_meal(true);
+        assert_eq!(game.cell(Position::new(4, 3)), Cell::Food);
+        assert_eq!(game.food_count(), 1);
+    }
+
+    #[test]
+    fn without_food_at_tail_the_vacated_cell_stays_empty() {
+        let game = second_meal(false);
+        assert_eq!(game.cell(Position::new(4, 3)), Cell::Empty);
+        assert_eq!(game.food_count(), 0);
+    }
+
+    #[test]
+    fn next_vacated_is_the_tail_unless_nothing_leaves() {
+        let mut game = playing(TAIL_MEAL);
+        game.update();
+        assert_eq!(game.next_vacated(), None);
+        run(&mut game, 2);
+        assert_eq!(game.next_vacated(), Some(game.tail_at()));
+        let tron = game_with(TAIL_MEAL, GameConfig {food_target: 0, tron: true, ..GameConfig::default()});
+        assert_eq!(tron.next_vacated(), None);
+    }
+
+    const MOVER_ABOVE: &str =
+        "##########
+         #        #
+         #        #
+         #    |   #
+         #   <    #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    #[test]
+    fn a_mover_crushing_player_one_ends_the_round_at_once() {
+        let mut game = playing(MOVER_ABOVE);
+        game.set_two_player(true);
+        game.mover_countdown = 1;
+        game.update();
+        assert_eq!(game.status(), Status::GameOver);
+        assert_eq!(game.winner(), Some(Winner::PlayerTwo));
+    }
+
+    #[test]
+    fn starving_in_two_player_hands_player_two_the_round() {
+        let mut game = game_with(BOX, GameConfig {food_target: 0, hunger: Some(1), ..GameConfig::default()});
+        game.set_hazards_frozen(true);
+        game.set_two_player(true);
+        game.update();
+        assert_eq!(game.status(), Status::GameOver);
+        assert_eq!(game.over_cause(), OverCause::Starved);
+        assert_eq!(game.winner(), Some(Winner::PlayerTwo));
+    }
+}
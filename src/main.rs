@@ -5,10 +5,11 @@ use lazy_static::lazy_static;
 use spin::Mutex;
 use pluggable_interrupt_os::HandlerTable;
 use pc_keyboard::DecodedKey;
-use baremetal_snake::{SnakeGame,MainGame};
+use x86_64::instructions::{hlt, interrupts};
+use baremetal_snake::{SnakeGame,MainGame,GameConfig};
 
 lazy_static! {
-    static ref GAME: Mutex<MainGame> = Mutex::new(SnakeGame::new());
+    static ref GAME: Mutex<MainGame> = Mutex::new(SnakeGame::new(GameConfig::default()));
 }
 
 fn tick() {
@@ -19,10 +20,29 @@ fn key(key: DecodedKey) {
     GAME.lock().key(key);
 }
 
+// Between interrupts the CPU writes out any save a finished run queued.
+// The game is only locked, with interrupts off so a handler can never
+// spin on it, long enough to take the save; the slow disk write happens
+// with interrupts back on. A write fails quietly when there is no save
+// disk attached.
+fn idle() -> ! {
+    loop {
+        if let Some(save) = interrupts::without_interrupts(|| GAME.lock().take_pending_save()) {
+            let _ = baremetal_snake::write_save(&save);
+        }
+        hlt();
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn _start() -> ! {
+    if let Ok(save) = baremetal_snake::load_save() {
+        GAME.lock().apply_save(&save);
+    }
+    baremetal_snake::restore_high_scores();
     HandlerTable::new()
         .keyboard(key)
         .timer(tick)
+        .cpu_loop(idle)
         .start()
 }
\ No newline at end of file
@@ -0,0 +1,113 @@
+use crate::{Cell, Position, Snake, Rng, Level, ALL_DIRS, MAX_MOVERS, MAX_ENEMIES};
+
+// Chambers narrower or shorter than this are left open so the snake has
+// room to turn around.
+const MIN_CHAMBER: usize = 9;
+const GAP_WIDTH: usize = 3;
+const MAX_CHAMBERS: usize = 64;
+
+#[derive(Copy,Clone)]
+struct Chamber {
+    top: usize, left: usize, bottom: usize, right: usize
+}
+
+// Recursive division over the interior, with walls only on even rows and
+// columns and every gap centred on an odd one. A later wall can then never
+// seal an earlier gap, so every open cell stays connected to the spawn.
+pub fn generate<const WIDTH: usize, const HEIGHT: usize>(rng: &mut Rng) -> Level<WIDTH,HEIGHT> {
+    let mut cells = [[Cell::Empty; WIDTH]; HEIGHT];
+    for (row, cells_row) in cells.iter_mut().enumerate() {
+        for (col, cell) in cells_row.iter_mut().enumerate() {
+            if row == 0 || col == 0 || row == HEIGHT - 1 || col == WIDTH - 1 {
+                *cell = Cell::Wall;
+            }
+        }
+    }
+    let mut stack = [Chamber {top: 0, left: 0, bottom: 0, right: 0}; MAX_CHAMBERS];
+    stack[0] = Chamber {top: 1, left: 1, bottom: HEIGHT - 2, right: WIDTH - 2};
+    let mut pending = 1;
+    while pending > 0 {
+        pending -= 1;
+        let chamber = stack[pending];
+        for half in divide(&mut cells, chamber, rng).iter().flatten() {
+            if pending < MAX_CHAMBERS {
+                stack[pending] = *half;
+                pending += 1;
+            }
+        }
+    }
+    let start = odd_below(HEIGHT / 2).max(1);
+    let start = Position::new(start, odd_below(WIDTH / 2).max(1));
+    let mut snake = Snake::new(start, '>');
+    if let Some(dir) = ALL_DIRS.iter().find(|d| {
+        let (row, col) = start.neighbor(**d).row_col();
+        cells[row][col] == Cell::Empty
+    }) {
+        snake.dir = *dir;
+    }
+    Level {cells, start: snake, name: Some("Maze"), par: None, time_limit: None, movers: [None; MAX_MOVERS], enemies: [None; MAX_ENEMIES]}
+}
+
+fn divide<const WIDTH: usize, const HEIGHT: usize>(cells: &mut [[Cell; WIDTH]; HEIGHT], c: Chamber, rng: &mut Rng) -> [Option<Chamber>; 2] {
+    let width = c.right + 1 - c.left;
+    let height = c.bottom + 1 - c.top;
+    if width < MIN_CHAMBER && height < MIN_CHAMBER {
+        return [None, None];
+    }
+    let horizontal = if width < MIN_CHAMBER {
+        true
+    } else if height < MIN_CHAMBER {
+        false
+    } else {
+        height > width || (height == width && rng.below(2) == 0)
+    };
+    if horizontal {
+        let row = match pick_even(c.top, c.bottom, rng) {
+            Some(row) => row,
+            None => return [None, None]
+        };
+        let gap = pick_odd(c.left, c.right, rng);
+        for (i, cell) in cells[row][c.left..=c.right].iter_mut().enumerate() {
+            if !in_gap(c.left + i, gap) {
+                *cell = Cell::Wall;
+            }
+        }
+        [Some(Chamber {bottom: row - 1, ..c}), Some(Chamber {top: row + 1, ..c})]
+    } else {
+        let col = match pick_even(c.left, c.right, rng) {
+            Some(col) => col,
+            None => return [None, None]
+        };
+        let gap = pick_odd(c.top, c.bottom, rng);
+        for (i, cells_row) in cells[c.top..=c.bottom].iter_mut().enumerate() {
+            if !in_gap(c.top + i, gap) {
+                cells_row[col] = Cell::Wall;
+            }
+        }
+        [Some(Chamber {right: col - 1, ..c}), Some(Chamber {left: col + 1, ..c})]
+    }
+}
+
+// An even line strictly inside `low..=high`.
+fn pick_even(low: usize, high: usize, rng: &mut Rng) -> Option<usize> {
+    let first = (low + 2) & !1;
+    if first >= high {
+        None
+    } else {
+        Some(first + 2 * rng.below((high - 1 - first) / 2 + 1))
+    }
+}
+
+// An odd cell in `low..=high`; chambers always start on an odd line.
+fn pick_odd(low: usize, high: usize, rng: &mut Rng) -> usize {
+    let first = low | 1;
+    first + 2 * rng.below((high.max(first) - first) / 2 + 1)
+}
+
+fn in_gap(i: usize, gap: usize) -> bool {
+    i + GAP_WIDTH / 2 >= gap && i <= gap + GAP_WIDTH / 2
+}
+
+fn odd_below(n: usize) -> usize {
+    if n % 2 == 1 {n} else {n.saturating_sub(1)}
+}
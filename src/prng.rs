@@ -0,0 +1,37 @@
+// xorshift64* -- small, fast, and good enough for picking cells.
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct Rng {
+    state: u64
+}
+
+const FALLBACK_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng {state: if seed == 0 {FALLBACK_SEED} else {seed}}
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // Rng::new(state) rebuilds the generator exactly.
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn mix(&mut self, entropy: u64) {
+        *self = Rng::new(self.state ^ entropy.wrapping_mul(FALLBACK_SEED));
+    }
+}
+
+pub fn tsc_seed() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
@@ -0,0 +1,171 @@
+use pluggable_interrupt_os::vga_buffer::{self, BUFFER_WIDTH, BUFFER_HEIGHT, Color};
+
+// Longest isize, sign included.
+const NUM_LEN: usize = 20;
+
+// Unlike the VGA attribute byte this keeps both colours readable, which
+// backends other than the VGA buffer need.
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct ColorCode {
+    foreground: Color,
+    background: Color,
+}
+
+impl ColorCode {
+    pub const fn new(foreground: Color, background: Color) -> Self {
+        ColorCode {foreground, background}
+    }
+
+    pub fn foreground(&self) -> Color {
+        self.foreground
+    }
+
+    pub fn background(&self) -> Color {
+        self.background
+    }
+
+    fn vga(&self) -> vga_buffer::ColorCode {
+        vga_buffer::ColorCode::new(self.foreground, self.background)
+    }
+}
+
+// Where the game draws itself. Positions are screen columns and rows of
+// an 80x25 text screen.
+pub trait Renderer {
+    fn plot(&mut self, c: char, col: usize, row: usize, color: ColorCode);
+    fn plot_str(&mut self, s: &str, col: usize, row: usize, color: ColorCode);
+    fn clear_row(&mut self, row: usize, background: Color);
+
+    fn plot_num(&mut self, num: isize, col: usize, row: usize, color: ColorCode) {
+        let mut digits = [0u8; NUM_LEN];
+        let mut start = NUM_LEN;
+        let mut rest = (num as i128).abs() as u64;
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (rest % 10) as u8;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        if num < 0 {
+            start -= 1;
+            digits[start] = b'-';
+        }
+        self.plot_str(core::str::from_utf8(&digits[start..]).unwrap_or(""), col, row, color);
+    }
+}
+
+// One character cell as the game drew it.
+type ScreenChar = (char, ColorCode);
+
+const BLANK: ScreenChar = (' ', ColorCode::new(Color::White, Color::Black));
+
+// The real screen. A frame is drawn into the back buffer, then flush
+// copies the cells that differ from what VGA memory already shows in one
+// pass, so a header that is cleared and redrawn never tears.
+pub struct Vga {
+    back: [[ScreenChar; BUFFER_WIDTH]; BUFFER_HEIGHT],
+    front: [[Option<ScreenChar>; BUFFER_WIDTH]; BUFFER_HEIGHT],
+}
+
+impl Default for Vga {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vga {
+    pub const fn new() -> Self {
+        Vga {back: [[BLANK; BUFFER_WIDTH]; BUFFER_HEIGHT], front: [[None; BUFFER_WIDTH]; BUFFER_HEIGHT]}
+    }
+
+    pub fn flush(&mut self) {
+        for row in 0..BUFFER_HEIGHT {
+            for col in 0..BUFFER_WIDTH {
+                let cell = self.back[row][col];
+                if self.front[row][col] != Some(cell) {
+                    vga_buffer::plot(cell.0, col, row, cell.1.vga());
+                    self.front[row][col] = Some(cell);
+                }
+            }
+        }
+    }
+
+    // For when something else has written to VGA memory behind our back.
+    pub fn invalidate(&mut self) {
+        self.front = [[None; BUFFER_WIDTH]; BUFFER_HEIGHT];
+    }
+}
+
+impl Renderer for Vga {
+    fn plot(&mut self, c: char, col: usize, row: usize, color: ColorCode) {
+        if row < BUFFER_HEIGHT && col < BUFFER_WIDTH {
+            self.back[row][col] = (c, color);
+        }
+    }
+
+    fn plot_str(&mut self, s: &str, col: usize, row: usize, color: ColorCode) {
+        for (i, c) in s.chars().enumerate() {
+            self.plot(c, col + i, row, color);
+        }
+    }
+
+    fn clear_row(&mut self, row: usize, background: Color) {
+        for col in 0..BUFFER_WIDTH {
+            self.plot(' ', col, row, ColorCode::new(Color::White, background));
+        }
+    }
+}
+
+// A screen in memory, so tests can look at what was drawn.
+#[cfg(test)]
+pub(crate) struct GridRenderer {
+    pub(crate) cells: [[ScreenChar; BUFFER_WIDTH]; BUFFER_HEIGHT],
+}
+
+#[cfg(test)]
+impl GridRenderer {
+    pub(crate) fn new() -> Self {
+        GridRenderer {cells: [[BLANK; BUFFER_WIDTH]; BUFFER_HEIGHT]}
+    }
+
+    pub(crate) fn text(&self, row: usize) -> String {
+        self.cells[row].iter().map(|(c, _)| *c).collect()
+    }
+}
+
+#[cfg(test)]
+impl Renderer for GridRenderer {
+    fn plot(&mut self, c: char, col: usize, row: usize, color: ColorCode) {
+        if row < BUFFER_HEIGHT && col < BUFFER_WIDTH {
+            self.cells[row][col] = (c, color);
+        }
+    }
+
+    fn plot_str(&mut self, s: &str, col: usize, row: usize, color: ColorCode) {
+        for (i, c) in s.chars().enumerate() {
+            self.plot(c, col + i, row, color);
+        }
+    }
+
+    fn clear_row(&mut self, row: usize, background: Color) {
+        for col in 0..BUFFER_WIDTH {
+            self.plot(' ', col, row, ColorCode::new(Color::White, background));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_plotted_with_their_sign() {
+        let mut grid = GridRenderer::new();
+        grid.plot_num(-42, 3, 0, BLANK.1);
+        grid.plot_num(0, 3, 1, BLANK.1);
+        assert!(grid.text(0).starts_with("   -42 "));
+        assert!(grid.text(1).starts_with("   0 "));
+    }
+}
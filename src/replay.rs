@@ -0,0 +1,85 @@
+use crate::{SnakeGame, Dir, Rng, ALL_DIRS};
+
+const MAX_REPLAY_MOVES: usize = 4096;
+const MOVES_PER_BYTE: usize = 4;
+
+// Everything a run needs to be played again: the PRNG as it stood when
+// the level was loaded and each move's direction packed two bits apiece.
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub(crate) struct Replay {
+    rng: Rng,
+    level: usize,
+    countdown: usize,
+    moves: [u8; MAX_REPLAY_MOVES / MOVES_PER_BYTE],
+    len: usize,
+}
+
+impl Replay {
+    pub(crate) fn new(rng: Rng) -> Self {
+        Replay {rng, level: 0, countdown: 0, moves: [0; MAX_REPLAY_MOVES / MOVES_PER_BYTE], len: 0}
+    }
+
+    pub(crate) fn begin(&mut self, rng: Rng, level: usize, countdown: usize) {
+        *self = Replay {level, countdown, ..Replay::new(rng)};
+    }
+
+    // Moves past MAX_REPLAY_MOVES are dropped; playback stops there.
+    pub(crate) fn push(&mut self, dir: Dir) {
+        if self.len < MAX_REPLAY_MOVES {
+            let bits = ALL_DIRS.iter().position(|d| *d == dir).unwrap_or(0) as u8;
+            let shift = 2 * (self.len % MOVES_PER_BYTE);
+            let byte = &mut self.moves[self.len / MOVES_PER_BYTE];
+            *byte = *byte & !(0b11 << shift) | bits << shift;
+            self.len += 1;
+        }
+    }
+
+    pub(crate) fn get(&self, i: usize) -> Option<Dir> {
+        if i < self.len {
+            let shift = 2 * (i % MOVES_PER_BYTE);
+            Some(ALL_DIRS[(self.moves[i / MOVES_PER_BYTE] >> shift & 0b11) as usize])
+        } else {
+            None
+        }
+    }
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    pub fn replay_len(&self) -> usize {
+        self.replay.len
+    }
+
+    pub fn is_playback(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    // Rewinds to the recorded start of the last run and plays its moves
+    // back. Only available once that run is over, and never for two
+    // players since only player one's moves are recorded.
+    pub fn start_playback(&mut self) -> bool {
+        if !self.is_finished() || self.replay.len == 0 || self.two_player {
+            return false;
+        }
+        let replay = self.replay;
+        self.rng = replay.rng;
+        self.countdown = replay.countdown;
+        self.restart_run(replay.level);
+        self.playback = Some(0);
+        true
+    }
+
+    pub(crate) fn stop_playback(&mut self) {
+        if self.playback.is_some() {
+            self.end_game(self.over_cause);
+            self.playback = None;
+        }
+    }
+
+    // None once a truncated recording runs out of moves.
+    pub(crate) fn next_playback_dir(&mut self) -> Option<Dir> {
+        let cursor = self.playback?;
+        let dir = self.replay.get(cursor)?;
+        self.playback = Some(cursor + 1);
+        Some(dir)
+    }
+}
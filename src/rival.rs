@@ -0,0 +1,328 @@
+use crate::{SnakeGame, Snake, Body, Position, Cell, Dir, DirList, InputQueue, OverCause, DecodeError, ALL_DIRS, FOOD_POINTS};
+use crate::snapshot::{ByteWriter, ByteReader, write_snake, read_snake, write_body, read_body, write_inputs, read_inputs};
+
+const RIVAL_START_GROWTH: u32 = 3;
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub(crate) struct Rival<const WIDTH: usize, const HEIGHT: usize> {
+    snake: Snake<WIDTH,HEIGHT>,
+    body: Body<WIDTH,HEIGHT>,
+    pending_growth: u32,
+    human: bool,
+    inputs: InputQueue,
+    points: u32,
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> Rival<WIDTH,HEIGHT> {
+    pub(crate) fn body_iter(&self) -> impl Iterator<Item=Position<WIDTH,HEIGHT>> + '_ {
+        self.body.iter()
+    }
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum Winner {
+    PlayerOne,
+    PlayerTwo,
+    Draw,
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    pub fn set_rival(&mut self, enabled: bool) {
+        self.rival_enabled = enabled;
+        self.remove_rival();
+        if enabled {
+            self.spawn_rival();
+        }
+    }
+
+    // Player two drives the rival snake from WASD instead of the AI.
+    pub fn set_two_player(&mut self, two_player: bool) {
+        self.two_player = two_player;
+        self.set_rival(two_player);
+    }
+
+    pub fn two_player(&self) -> bool {
+        self.two_player
+    }
+
+    pub fn player_two_score(&self) -> Option<u32> {
+        self.rival.filter(|rival| rival.human).map(|rival| rival.points)
+    }
+
+    pub fn winner(&self) -> Option<Winner> {
+        self.winner
+    }
+
+    pub(crate) fn queue_rival_dir(&mut self, dir: Dir) {
+        if let Some(rival) = self.rival.as_mut() {
+            let last = rival.inputs.back().unwrap_or(rival.snake.dir);
+            if dir != last && dir != last.opposite() {
+                rival.inputs.push(dir);
+            }
+        }
+    }
+
+    pub fn rival_head(&self) -> Option<Position<WIDTH,HEIGHT>> {
+        self.rival.map(|rival| rival.snake.pos)
+    }
+
+    pub fn rival_icon(&self) -> Option<char> {
+        self.rival.map(|rival| rival.snake.icon())
+    }
+
+    pub fn rival_length(&self) -> Option<usize> {
+        self.rival.map(|rival| 1 + rival.body.len())
+    }
+
+    // Player two starts opposite player one so neither gets a head start.
+    pub(crate) fn spawn_rival(&mut self) {
+        let (row, col) = self.start.pos.row_col();
+        let mirrored = Position::new(HEIGHT - 1 - row, WIDTH - 1 - col);
+        let spot = if self.two_player && self.cell(mirrored) == Cell::Empty && mirrored != self.snake.pos {
+            Some(mirrored)
+        } else {
+            self.random_empty_cell()
+        };
+        if let Some(p) = spot {
+            let dir = if self.two_player {
+                self.start.dir.opposite()
+            } else {
+                ALL_DIRS.iter().copied()
+                    .find(|d| self.rival_can_enter(self.next_pos(p, *d)))
+                    .unwrap_or(Dir::E)
+            };
+            let mut snake = Snake::new(p, '>');
+            snake.dir = dir;
+            self.rival = Some(Rival {
+                snake, body: Body::new(), pending_growth: RIVAL_START_GROWTH,
+                human: self.two_player, inputs: InputQueue::new(), points: 0
+            });
+        }
+    }
+
+    pub(crate) fn remove_rival(&mut self) {
+        if let Some(mut rival) = self.rival.take() {
+            while let Some(p) = rival.body.pop_back() {
+                let (row, col) = p.row_col();
+                self.cells[row][col] = Cell::Empty;
+            }
+        }
+    }
+
+    // Runs after player one has moved from `player_from`. The AI rival
+    // just dies when it has nowhere safe to go; with two players any
+    // death ends the round, and crashing on the same move, meeting
+    // head-on or swapping cells kills both snakes.
+    pub(crate) fn update_rival(&mut self, player_from: Position<WIDTH,HEIGHT>) {
+        let player_crashed = self.two_player && self.winner.is_some();
+        if let Some(mut rival) = self.rival {
+            let dir = if rival.human {
+                match rival.inputs.pop() {
+                    Some(dir) if dir != rival.snake.dir.opposite() => dir,
+                    _ => rival.snake.dir
+                }
+            } else {
+                self.rival_choice(&rival)
+            };
+            let from = rival.snake.pos;
+            let next = self.next_pos(from, dir);
+            if rival.human {
+                let head_on = next == self.snake.pos;
+                let swapped = next == player_from && self.snake.pos == from;
+                let rival_crashed = head_on || swapped || !self.rival_can_enter(next);
+                if player_crashed || rival_crashed {
+                    self.finish_round(match (player_crashed, rival_crashed) {
+                        (true, false) => Winner::PlayerTwo,
+                        (false, true) if !head_on && !swapped => Winner::PlayerOne,
+                        _ => Winner::Draw
+                    });
+                    return;
+                }
+            } else if !self.rival_can_enter(next) {
+                self.remove_rival();
+                return;
+            }
+            let neck = rival.snake.pos;
+            if self.config.tron {
+                let (row, col) = neck.row_col();
+                self.cells[row][col] = Cell::RivalBody;
+                rival.points += 1;
+            } else if rival.pending_growth > 0 && !rival.body.is_full() {
+                rival.pending_growth -= 1;
+                self.push_rival_body(&mut rival, neck);
+            } else if let Some(tail) = rival.body.pop_back() {
+                let (row, col) = tail.row_col();
+                self.cells[row][col] = Cell::Empty;
+                self.push_rival_body(&mut rival, neck);
+            }
+            rival.snake.pos = next;
+            rival.snake.dir = dir;
+            rival.snake.tick();
+            let ate = self.cell(next) == Cell::Food;
+            if ate {
+                let (row, col) = next.row_col();
+                self.cells[row][col] = Cell::Empty;
+                self.food_on_board -= 1;
+                rival.pending_growth += 1;
+                rival.points += FOOD_POINTS;
+            }
+            self.rival = Some(rival);
+            if ate {
+                self.top_up_food();
+            }
+            if rival.human && self.cell(self.snake.pos) == Cell::RivalBody {
+                self.finish_round(Winner::PlayerTwo);
+            }
+        } else if player_crashed {
+            self.finish_round(Winner::PlayerTwo);
+        }
+    }
+
+    pub(crate) fn finish_round(&mut self, winner: Winner) {
+        self.winner = Some(winner);
+        self.end_game(OverCause::Crashed);
+    }
+
+    fn push_rival_body(&mut self, rival: &mut Rival<WIDTH,HEIGHT>, p: Position<WIDTH,HEIGHT>) {
+        let (row, col) = p.row_col();
+        self.cells[row][col] = Cell::RivalBody;
+        rival.body.push_front(p);
+    }
+
+    fn rival_can_enter(&self, p: Position<WIDTH,HEIGHT>) -> bool {
+        p.is_legal() && p != self.snake.pos && match self.cell(p) {
+            Cell::Empty | Cell::Food => true,
+            _ => false
+        }
+    }
+
+    // Greedy: step toward the nearest food, keeping straight on if no
+    // safe step gets closer. Ties go to the first direction in ALL_DIRS.
+    fn rival_choice(&self, rival: &Rival<WIDTH,HEIGHT>) -> Dir {
+        let target = match self.nearest_food(rival.snake.pos) {
+            Some(target) => target,
+            None => return self.rival_safe_dirs(rival).as_slice().first().copied().unwrap_or(rival.snake.dir)
+        };
+        let mut best: Option<(usize, Dir)> = None;
+        for d in self.rival_safe_dirs(rival).as_slice() {
+            let distance = manhattan(self.next_pos(rival.snake.pos, *d), target);
+            if best.map_or(true, |(closest, _)| distance < closest) {
+                best = Some((distance, *d));
+            }
+        }
+        best.map_or(rival.snake.dir, |(_, d)| d)
+    }
+
+    fn rival_safe_dirs(&self, rival: &Rival<WIDTH,HEIGHT>) -> DirList {
+        let mut dirs = DirList::new();
+        for d in ALL_DIRS.iter() {
+            if *d != rival.snake.dir.opposite() && self.rival_can_enter(self.next_pos(rival.snake.pos, *d)) {
+                dirs.push(*d);
+            }
+        }
+        dirs
+    }
+
+    // Equidistant food is broken by lowest row, then lowest column.
+    pub fn nearest_food(&self, from: Position<WIDTH,HEIGHT>) -> Option<Position<WIDTH,HEIGHT>> {
+        let mut best: Option<(usize, Position<WIDTH,HEIGHT>)> = None;
+        for p in self.food_positions() {
+            let distance = manhattan(from, p);
+            if best.map_or(true, |(closest, _)| distance < closest) {
+                best = Some((distance, p));
+            }
+        }
+        best.map(|(_, p)| p)
+    }
+
+    pub(crate) fn write_rival(&self, w: &mut ByteWriter) {
+        w.u8(self.rival.is_some() as u8);
+        if let Some(rival) = self.rival {
+            write_snake(w, &rival.snake);
+            write_body(w, &rival.body);
+            w.u32(rival.pending_growth);
+            w.u8(rival.human as u8);
+            write_inputs(w, &rival.inputs);
+            w.u32(rival.points);
+        }
+    }
+
+    pub(crate) fn read_rival(r: &mut ByteReader) -> Result<Option<Rival<WIDTH,HEIGHT>>, DecodeError> {
+        if r.u8()? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Rival {
+            snake: read_snake(r)?,
+            body: read_body(r)?,
+            pending_growth: r.u32()?,
+            human: r.u8()? != 0,
+            inputs: read_inputs(r)?,
+            points: r.u32()?
+        }))
+    }
+}
+
+pub(crate) fn manhattan<const WIDTH: usize, const HEIGHT: usize>(a: Position<WIDTH,HEIGHT>, b: Position<WIDTH,HEIGHT>) -> usize {
+    let ((a_row, a_col), (b_row, b_col)) = (a.row_col(), b.row_col());
+    let rows = if a_row > b_row {a_row - b_row} else {b_row - a_row};
+    let cols = if a_col > b_col {a_col - b_col} else {b_col - a_col};
+    rows + cols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameConfig, MapSource, Status};
+
+    type Small = SnakeGame<10,10>;
+
+    const FACE_OFF: &str =
+        "##########
+         #        #
+         #        #
+         #        #
+         #   <    #
+         #        #
+         #        #
+         #        #
+         #        #
+         ##########";
+
+    // Player two is moved from its mirrored spawn to `(row, col)`,
+    // heading West towards player one.
+    fn face_off(row: usize, col: usize) -> Small {
+        let config = GameConfig {seed: Some(1), food_target: 0, ..GameConfig::default()};
+        let mut game = Small::from_source(MapSource::Text(FACE_OFF), config, &[]);
+        game.start();
+        game.set_hazards_frozen(true);
+        game.set_two_player(true);
+        let rival = game.rival.as_mut().unwrap();
+        rival.snake.pos = Position::new(row, col);
+        rival.snake.dir = Dir::W;
+        game
+    }
+
+    #[test]
+    fn swapping_cells_kills_both_snakes() {
+        let mut game = face_off(4, 5);
+        game.update();
+        assert_eq!(game.status(), Status::GameOver);
+        assert_eq!(game.winner(), Some(Winner::Draw));
+    }
+
+    #[test]
+    fn meeting_head_on_kills_both_snakes() {
+        let mut game = face_off(4, 6);
+        game.update();
+        assert_eq!(game.status(), Status::GameOver);
+        assert_eq!(game.winner(), Some(Winner::Draw));
+    }
+
+    #[test]
+    fn snakes_that_miss_each_other_play_on() {
+        let mut game = face_off(5, 6);
+        game.update();
+        assert_eq!(game.status(), Status::Playing);
+        assert_eq!(game.winner(), None);
+    }
+}
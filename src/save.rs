@@ -0,0 +1,225 @@
+#[cfg(target_os = "none")]
+use spin::Mutex;
+use crate::{SnakeGame, Difficulty, DecodeError, HighScore, HighScores, Achievements};
+use crate::high_scores::{high_scores, record_high_score};
+use crate::ata::AtaError;
+#[cfg(target_os = "none")]
+use crate::ata::{AtaDrive, SECTOR_SIZE};
+use crate::snapshot::{ByteWriter, ByteReader};
+
+const SAVE_MAGIC: &[u8; 4] = b"SNKS";
+const SAVE_VERSION: u8 = 2;
+// The save gets the first sector of its own disk (QEMU: -drive index=1)
+// so writing it can never clobber the boot image.
+#[cfg(target_os = "none")]
+const SAVE_LBA: u32 = 0;
+
+#[cfg(target_os = "none")]
+static SAVE_DRIVE: Mutex<AtaDrive> = Mutex::new(AtaDrive::primary_slave());
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub struct SaveData {
+    pub scores: HighScores,
+    pub difficulty: Difficulty,
+    pub endless: bool,
+    pub unlocked: usize,
+    pub achievements: Achievements,
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum SaveError {
+    Disk(AtaError),
+    Decode(DecodeError),
+}
+
+impl Difficulty {
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 2
+        }
+    }
+
+    pub(crate) fn from_byte(b: u8) -> Result<Difficulty, DecodeError> {
+        match b {
+            0 => Ok(Difficulty::Easy),
+            1 => Ok(Difficulty::Normal),
+            2 => Ok(Difficulty::Hard),
+            _ => Err(DecodeError::BadValue(b))
+        }
+    }
+}
+
+// Layout: magic, version, difficulty, endless, unlocked stages,
+// achievement bits (u32), score count, then (u32 score, u8 level) per
+// score and a trailing checksum.
+impl SaveData {
+    pub fn encode(&self, out: &mut [u8]) -> usize {
+        let mut w = ByteWriter::new(out);
+        for b in SAVE_MAGIC.iter() {
+            w.u8(*b);
+        }
+        w.u8(SAVE_VERSION);
+        w.u8(self.difficulty.to_byte());
+        w.u8(self.endless as u8);
+        w.u8(self.unlocked.min(u8::MAX as usize) as u8);
+        w.u32(self.achievements.bits());
+        w.u8(self.scores.entries().count() as u8);
+        for entry in self.scores.entries() {
+            w.u32(entry.score);
+            w.u8(entry.level.min(u8::MAX as usize) as u8);
+        }
+        let len = w.finish();
+        if len == 0 || len == out.len() {
+            return 0;
+        }
+        out[len] = checksum(&out[..len]);
+        len + 1
+    }
+
+    pub fn decode(data: &[u8]) -> Result<SaveData, DecodeError> {
+        let mut r = ByteReader::new(data);
+        for b in SAVE_MAGIC.iter() {
+            let found = r.u8()?;
+            if found != *b {
+                return Err(DecodeError::BadValue(found));
+            }
+        }
+        let version = r.u8()?;
+        if version != SAVE_VERSION {
+            return Err(DecodeError::BadVersion(version));
+        }
+        let difficulty = Difficulty::from_byte(r.u8()?)?;
+        let endless = r.u8()? != 0;
+        let unlocked = r.u8()? as usize;
+        let achievements = Achievements::from_bits(r.u32()?);
+        let mut scores = HighScores::new();
+        for _ in 0..r.u8()? {
+            let score = r.u32()?;
+            scores.insert(HighScore {score, level: r.u8()? as usize});
+        }
+        let len = r.position();
+        if r.u8()? != checksum(&data[..len]) {
+            return Err(DecodeError::BadChecksum);
+        }
+        Ok(SaveData {scores, difficulty, endless, unlocked, achievements})
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum: u8, b| sum.rotate_left(1) ^ *b)
+}
+
+#[cfg(target_os = "none")]
+pub fn load_save() -> Result<SaveData, SaveError> {
+    let mut sector = [0; SECTOR_SIZE];
+    SAVE_DRIVE.lock().read_sector(SAVE_LBA, &mut sector).map_err(SaveError::Disk)?;
+    SaveData::decode(&sector).map_err(SaveError::Decode)
+}
+
+#[cfg(target_os = "none")]
+pub fn write_save(save: &SaveData) -> Result<(), SaveError> {
+    let mut sector = [0; SECTOR_SIZE];
+    save.encode(&mut sector);
+    SAVE_DRIVE.lock().write_sector(SAVE_LBA, &sector).map_err(SaveError::Disk)
+}
+
+// Port I/O would fault in a host process, so there the save disk is
+// simply missing.
+#[cfg(not(target_os = "none"))]
+pub fn load_save() -> Result<SaveData, SaveError> {
+    Err(SaveError::Disk(AtaError::NoDrive))
+}
+
+#[cfg(not(target_os = "none"))]
+pub fn write_save(_save: &SaveData) -> Result<(), SaveError> {
+    Err(SaveError::Disk(AtaError::NoDrive))
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    pub fn save_data(&self) -> SaveData {
+        SaveData {
+            scores: high_scores(),
+            difficulty: self.difficulty,
+            endless: self.config.endless,
+            unlocked: self.campaign.unlocked(),
+            achievements: self.achievements
+        }
+    }
+
+    pub fn apply_save(&mut self, save: &SaveData) {
+        self.set_difficulty(save.difficulty);
+        self.set_endless(save.endless);
+        self.campaign.unlock(save.unlocked);
+        self.achievements.merge(save.achievements);
+        for entry in save.scores.entries() {
+            record_high_score(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save_data() -> SaveData {
+        let mut scores = HighScores::new();
+        for (score, level) in [(900, 4), (40, 1), (350, 2)].iter() {
+            scores.insert(HighScore {score: *score, level: *level});
+        }
+        SaveData {
+            scores,
+            difficulty: Difficulty::Hard,
+            endless: true,
+            unlocked: 3,
+            achievements: Achievements::from_bits(u32::MAX)
+        }
+    }
+
+    fn encoded() -> ([u8; 64], usize) {
+        let mut buf = [0; 64];
+        let len = save_data().encode(&mut buf);
+        assert!(len > 0);
+        (buf, len)
+    }
+
+    #[test]
+    fn a_save_decodes_to_itself() {
+        let (buf, len) = encoded();
+        assert_eq!(SaveData::decode(&buf[..len]), Ok(save_data()));
+    }
+
+    #[test]
+    fn a_bad_checksum_is_refused() {
+        let (mut buf, len) = encoded();
+        buf[len - 1] ^= 1;
+        assert_eq!(SaveData::decode(&buf[..len]), Err(DecodeError::BadChecksum));
+        let (mut buf, len) = encoded();
+        buf[len - 2] ^= 1;
+        assert_eq!(SaveData::decode(&buf[..len]), Err(DecodeError::BadChecksum));
+    }
+
+    #[test]
+    fn a_bad_magic_is_refused() {
+        let (mut buf, len) = encoded();
+        buf[0] = b'X';
+        assert_eq!(SaveData::decode(&buf[..len]), Err(DecodeError::BadValue(b'X')));
+        assert_eq!(SaveData::decode(&[0; 64]), Err(DecodeError::BadValue(0)));
+    }
+
+    #[test]
+    fn other_versions_and_short_saves_are_refused() {
+        let (mut buf, len) = encoded();
+        assert_eq!(SaveData::decode(&buf[..len - 1]), Err(DecodeError::Truncated));
+        buf[SAVE_MAGIC.len()] = SAVE_VERSION + 1;
+        assert_eq!(SaveData::decode(&buf[..len]), Err(DecodeError::BadVersion(SAVE_VERSION + 1)));
+    }
+
+    #[test]
+    fn a_buffer_too_small_encodes_nothing() {
+        let (_, len) = encoded();
+        let mut small = [0; 64];
+        assert_eq!(save_data().encode(&mut small[..len - 1]), 0);
+    }
+}
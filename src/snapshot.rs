@@ -0,0 +1,999 @@
+use spin::Mutex;
+use crate::{SnakeGame, Snake, Body, Position, Cell, Dir, Status, InputQueue, GameConfig, BoardMode, Layout, OverCause,
+    Difficulty, Rng, Wanderer, Winner, ScoreBreakdown, MAX_BODY, MAX_PORTALS, MAX_MOVERS, MAX_ENEMIES, MAX_WANDERERS,
+    INPUT_QUEUE_LEN};
+use crate::effects::{ALL_EFFECTS, EFFECT_KINDS};
+use crate::level::Mover;
+
+const DIFF_VERSION: u8 = 2;
+const SNAPSHOT_VERSION: u8 = 3;
+// Room for a full board plus two maxed-out bodies.
+const QUICK_SAVE_LEN: usize = 12 * 1024;
+const QUICK_SAVE_TOAST_FRAMES: u64 = 30;
+const PORTAL_BYTE: u8 = 16;
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+pub enum DecodeError {
+    Truncated,
+    BadVersion(u8),
+    BadValue(u8),
+    OutOfBounds,
+    BadChecksum,
+    // Every field decodes but together they describe a game that could
+    // never have been played.
+    Inconsistent,
+}
+
+pub(crate) struct ByteWriter<'a> {
+    buf: &'a mut [u8], len: usize, overflow: bool
+}
+
+impl <'a> ByteWriter<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+        ByteWriter {buf, len: 0, overflow: false}
+    }
+
+    pub(crate) fn u8(&mut self, b: u8) {
+        if self.len < self.buf.len() {
+            self.buf[self.len] = b;
+            self.len += 1;
+        } else {
+            self.overflow = true;
+        }
+    }
+
+    pub(crate) fn u16(&mut self, v: u16) {
+        for b in v.to_le_bytes().iter() {
+            self.u8(*b);
+        }
+    }
+
+    pub(crate) fn u32(&mut self, v: u32) {
+        for b in v.to_le_bytes().iter() {
+            self.u8(*b);
+        }
+    }
+
+    pub(crate) fn u64(&mut self, v: u64) {
+        for b in v.to_le_bytes().iter() {
+            self.u8(*b);
+        }
+    }
+
+    // A flag byte, then the value only when there is one.
+    pub(crate) fn opt_u32(&mut self, v: Option<u32>) {
+        self.u8(v.is_some() as u8);
+        if let Some(v) = v {
+            self.u32(v);
+        }
+    }
+
+    pub(crate) fn finish(self) -> usize {
+        if self.overflow {0} else {self.len}
+    }
+}
+
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8], pos: usize
+}
+
+impl <'a> ByteReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        ByteReader {data, pos: 0}
+    }
+
+    pub(crate) fn at(data: &'a [u8], pos: usize) -> Self {
+        ByteReader {data, pos}
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, DecodeError> {
+        let b = *self.data.get(self.pos).ok_or(DecodeError::Truncated)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes([self.u8()?, self.u8()?]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes([self.u8()?, self.u8()?, self.u8()?, self.u8()?]))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+
+    pub(crate) fn opt_u32(&mut self) -> Result<Option<u32>, DecodeError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.u32()?)),
+            b => Err(DecodeError::BadValue(b))
+        }
+    }
+
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    // A byte already read past, looked up again.
+    pub(crate) fn byte_at(&self, pos: usize) -> Result<u8, DecodeError> {
+        self.data.get(pos).copied().ok_or(DecodeError::Truncated)
+    }
+}
+
+impl Cell {
+    fn to_byte(self) -> u8 {
+        match self {
+            Cell::Food => 0,
+            Cell::Empty => 1,
+            Cell::Wall => 2,
+            Cell::Body => 3,
+            Cell::Bedrock => 4,
+            Cell::BonusFood => 5,
+            Cell::Poison => 6,
+            Cell::Ghost => 7,
+            Cell::SlowMotion => 8,
+            Cell::Multiplier => 9,
+            Cell::Shrink => 10,
+            Cell::RivalBody => 11,
+            Cell::Key => 12,
+            Cell::Door => 13,
+            Cell::MovingWall => 14,
+            Cell::WallBreaker => 15,
+            Cell::Portal(id) => PORTAL_BYTE + id
+        }
+    }
+
+    fn from_byte(b: u8) -> Result<Cell, DecodeError> {
+        match b {
+            0 => Ok(Cell::Food),
+            1 => Ok(Cell::Empty),
+            2 => Ok(Cell::Wall),
+            3 => Ok(Cell::Body),
+            4 => Ok(Cell::Bedrock),
+            5 => Ok(Cell::BonusFood),
+            6 => Ok(Cell::Poison),
+            7 => Ok(Cell::Ghost),
+            8 => Ok(Cell::SlowMotion),
+            9 => Ok(Cell::Multiplier),
+            10 => Ok(Cell::Shrink),
+            11 => Ok(Cell::RivalBody),
+            12 => Ok(Cell::Key),
+            13 => Ok(Cell::Door),
+            14 => Ok(Cell::MovingWall),
+            15 => Ok(Cell::WallBreaker),
+            b if b > PORTAL_BYTE && b <= PORTAL_BYTE + MAX_PORTALS as u8 => Ok(Cell::Portal(b - PORTAL_BYTE)),
+            _ => Err(DecodeError::BadValue(b))
+        }
+    }
+}
+
+impl Dir {
+    fn to_byte(self) -> u8 {
+        match self {
+            Dir::N => 0,
+            Dir::S => 1,
+            Dir::E => 2,
+            Dir::W => 3
+        }
+    }
+
+    fn from_byte(b: u8) -> Result<Dir, DecodeError> {
+        match b {
+            0 => Ok(Dir::N),
+            1 => Ok(Dir::S),
+            2 => Ok(Dir::E),
+            3 => Ok(Dir::W),
+            _ => Err(DecodeError::BadValue(b))
+        }
+    }
+}
+
+impl Status {
+    fn to_byte(self) -> u8 {
+        match self {
+            Status::Playing => 0,
+            Status::GameOver => 1,
+            Status::Error => 2,
+            Status::Paused => 3,
+            Status::Menu => 4,
+            Status::Victory => 5
+        }
+    }
+
+    fn from_byte(b: u8) -> Result<Status, DecodeError> {
+        match b {
+            0 => Ok(Status::Playing),
+            1 => Ok(Status::GameOver),
+            2 => Ok(Status::Error),
+            3 => Ok(Status::Paused),
+            4 => Ok(Status::Menu),
+            5 => Ok(Status::Victory),
+            _ => Err(DecodeError::BadValue(b))
+        }
+    }
+}
+
+pub(crate) fn write_pos<const WIDTH: usize, const HEIGHT: usize>(w: &mut ByteWriter, p: Position<WIDTH,HEIGHT>) {
+    let (row, col) = p.row_col();
+    w.u16(row as u16);
+    w.u16(col as u16);
+}
+
+pub(crate) fn read_pos<const WIDTH: usize, const HEIGHT: usize>(r: &mut ByteReader) -> Result<Position<WIDTH,HEIGHT>, DecodeError> {
+    let (row, col) = (r.u16()? as usize, r.u16()? as usize);
+    if row < HEIGHT && col < WIDTH {
+        Ok(Position::new(row, col))
+    } else {
+        Err(DecodeError::OutOfBounds)
+    }
+}
+
+pub(crate) fn write_snake<const WIDTH: usize, const HEIGHT: usize>(w: &mut ByteWriter, snake: &Snake<WIDTH,HEIGHT>) {
+    write_pos(w, snake.pos);
+    w.u8(snake.dir.to_byte());
+    w.u8(snake.open as u8);
+}
+
+pub(crate) fn read_snake<const WIDTH: usize, const HEIGHT: usize>(r: &mut ByteReader) -> Result<Snake<WIDTH,HEIGHT>, DecodeError> {
+    let pos = read_pos(r)?;
+    let dir = Dir::from_byte(r.u8()?)?;
+    let open = r.u8()? != 0;
+    Ok(Snake {pos, dir, open})
+}
+
+pub(crate) fn write_body<const WIDTH: usize, const HEIGHT: usize>(w: &mut ByteWriter, body: &Body<WIDTH,HEIGHT>) {
+    w.u16(body.len() as u16);
+    for p in body.iter() {
+        write_pos(w, p);
+    }
+}
+
+pub(crate) fn read_body<const WIDTH: usize, const HEIGHT: usize>(r: &mut ByteReader) -> Result<Body<WIDTH,HEIGHT>, DecodeError> {
+    let len = r.u16()? as usize;
+    if len > MAX_BODY {
+        return Err(DecodeError::OutOfBounds);
+    }
+    let mut body: Body<WIDTH,HEIGHT> = Body::new();
+    for _ in 0..len {
+        body.push_back(read_pos(r)?);
+    }
+    Ok(body)
+}
+
+// How many segments at the head end of `prev` are still the tail end of
+// `cur`. A body only grows at its head and shrinks at its tail, so
+// everything else in `cur` was laid since.
+fn kept_segments<const WIDTH: usize, const HEIGHT: usize>(prev: &Body<WIDTH,HEIGHT>, cur: &Body<WIDTH,HEIGHT>) -> usize {
+    (0..=prev.len().min(cur.len())).rev()
+        .find(|kept| (0..*kept).all(|i| prev.get(i) == cur.get(cur.len() - kept + i)))
+        .unwrap_or(0)
+}
+
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    // What changed from `prev` to `cur`: the snake, the segments its tail
+    // dropped and its head laid, the score and food eaten as deltas, and
+    // the cells that differ. Applied to `prev` it gives back `cur`'s
+    // board: the snake, body, pending growth, score, food eaten, status
+    // and every cell. Nothing else is carried; serialize is for that.
+    pub fn diff(prev: &Self, cur: &Self, out: &mut [u8]) -> usize {
+        let mut w = ByteWriter::new(out);
+        w.u8(DIFF_VERSION);
+        write_snake(&mut w, &cur.snake);
+        let kept = kept_segments(&prev.body, &cur.body);
+        let laid = cur.body.len() - kept;
+        w.u16((prev.body.len() - kept) as u16);
+        w.u16(laid as u16);
+        // Oldest first, so each goes onto the front in turn.
+        for i in (0..laid).rev() {
+            write_pos(&mut w, cur.body.get(i));
+        }
+        w.u32(cur.pending_growth);
+        w.u32(cur.points.wrapping_sub(prev.points));
+        w.u32(cur.food_eaten.wrapping_sub(prev.food_eaten));
+        w.u8(cur.status.to_byte());
+        let changed = |p: &Position<WIDTH,HEIGHT>| prev.cell(*p) != cur.cell(*p);
+        w.u16(cur.cell_pos_iter().filter(changed).count() as u16);
+        for p in cur.cell_pos_iter().filter(changed) {
+            write_pos(&mut w, p);
+            w.u8(cur.cell(p).to_byte());
+        }
+        w.finish()
+    }
+
+    // Nothing changes unless the whole diff decodes.
+    pub fn apply_diff(&mut self, data: &[u8]) -> Result<(), DecodeError> {
+        let mut r = ByteReader::new(data);
+        let version = r.u8()?;
+        if version != DIFF_VERSION {
+            return Err(DecodeError::BadVersion(version));
+        }
+        let snake = read_snake(&mut r)?;
+        let dropped = r.u16()? as usize;
+        let laid = r.u16()? as usize;
+        if dropped > self.body.len() || self.body.len() - dropped + laid > MAX_BODY {
+            return Err(DecodeError::OutOfBounds);
+        }
+        let laid_at = r.position();
+        for _ in 0..laid {
+            read_pos::<WIDTH,HEIGHT>(&mut r)?;
+        }
+        let pending_growth = r.u32()?;
+        let points = r.u32()?;
+        let food_eaten = r.u32()?;
+        let status = Status::from_byte(r.u8()?)?;
+        let changed = r.u16()?;
+        let cells_at = r.position();
+        for _ in 0..changed {
+            read_pos::<WIDTH,HEIGHT>(&mut r)?;
+            Cell::from_byte(r.u8()?)?;
+        }
+        self.snake = snake;
+        for _ in 0..dropped {
+            self.body.pop_back();
+        }
+        let mut r = ByteReader::at(data, laid_at);
+        for _ in 0..laid {
+            self.body.push_front(read_pos(&mut r)?);
+        }
+        self.pending_growth = pending_growth;
+        self.points = self.points.wrapping_add(points);
+        self.food_eaten = self.food_eaten.wrapping_add(food_eaten);
+        self.status = status;
+        let mut r = ByteReader::at(data, cells_at);
+        for _ in 0..changed {
+            let p: Position<WIDTH,HEIGHT> = read_pos(&mut r)?;
+            let (row, col) = p.row_col();
+            self.cells[row][col] = Cell::from_byte(r.u8()?)?;
+        }
+        self.recount_food();
+        Ok(())
+    }
+}
+
+impl OverCause {
+    fn to_byte(self) -> u8 {
+        match self {
+            OverCause::Crashed => 0,
+            OverCause::Starved => 1,
+            OverCause::Caught => 2,
+            OverCause::TimeUp => 3
+        }
+    }
+
+    fn from_byte(b: u8) -> Result<OverCause, DecodeError> {
+        match b {
+            0 => Ok(OverCause::Crashed),
+            1 => Ok(OverCause::Starved),
+            2 => Ok(OverCause::Caught),
+            3 => Ok(OverCause::TimeUp),
+            _ => Err(DecodeError::BadValue(b))
+        }
+    }
+}
+
+impl Winner {
+    fn to_byte(self) -> u8 {
+        match self {
+            Winner::PlayerOne => 0,
+            Winner::PlayerTwo => 1,
+            Winner::Draw => 2
+        }
+    }
+
+    fn from_byte(b: u8) -> Result<Winner, DecodeError> {
+        match b {
+            0 => Ok(Winner::PlayerOne),
+            1 => Ok(Winner::PlayerTwo),
+            2 => Ok(Winner::Draw),
+            _ => Err(DecodeError::BadValue(b))
+        }
+    }
+}
+
+// The board has to show a body everywhere its queue says one is, or the
+// tail would leave holes in whatever is really there.
+fn check_body<const WIDTH: usize, const HEIGHT: usize>(r: &ByteReader, cells_at: usize,
+    body: impl Iterator<Item=Position<WIDTH,HEIGHT>>, cell: Cell) -> Result<(), DecodeError> {
+    for p in body {
+        let (row, col) = p.row_col();
+        if Cell::from_byte(r.byte_at(cells_at + row * WIDTH + col)?)? != cell {
+            return Err(DecodeError::Inconsistent);
+        }
+    }
+    Ok(())
+}
+
+// A mover has to fit on the board from its lead all the way back to
+// its tail.
+fn read_mover<const WIDTH: usize, const HEIGHT: usize>(r: &mut ByteReader) -> Result<Mover<WIDTH,HEIGHT>, DecodeError> {
+    let lead = read_pos(r)?;
+    let len = r.u16()? as usize;
+    let dir = Dir::from_byte(r.u8()?)?;
+    if len == 0 || len > WIDTH.max(HEIGHT) {
+        return Err(DecodeError::OutOfBounds);
+    }
+    let mut p: Position<WIDTH,HEIGHT> = lead;
+    for _ in 1..len {
+        p = p.neighbor(dir.opposite());
+        if !p.is_legal() {
+            return Err(DecodeError::OutOfBounds);
+        }
+    }
+    Ok(Mover {lead, len, dir})
+}
+
+fn read_bool(r: &mut ByteReader) -> Result<bool, DecodeError> {
+    match r.u8()? {
+        0 => Ok(false),
+        1 => Ok(true),
+        b => Err(DecodeError::BadValue(b))
+    }
+}
+
+pub(crate) fn write_inputs(w: &mut ByteWriter, inputs: &InputQueue) {
+    w.u8(inputs.len as u8);
+    for d in inputs.dirs[..inputs.len].iter().copied() {
+        w.u8(d.to_byte());
+    }
+}
+
+pub(crate) fn read_inputs(r: &mut ByteReader) -> Result<InputQueue, DecodeError> {
+    let len = r.u8()? as usize;
+    if len > INPUT_QUEUE_LEN {
+        return Err(DecodeError::OutOfBounds);
+    }
+    let mut inputs = InputQueue::new();
+    for _ in 0..len {
+        inputs.push(Dir::from_byte(r.u8()?)?);
+    }
+    Ok(inputs)
+}
+
+fn write_config(w: &mut ByteWriter, config: &GameConfig) {
+    w.u32(config.win_threshold);
+    w.u16(config.food_target as u16);
+    w.u32(config.lives);
+    w.u16(config.speed as u16);
+    w.u8(match config.board_mode {BoardMode::Walled => 0, BoardMode::Wrapping => 1});
+    w.opt_u32(config.hunger);
+    w.u8(config.tron as u8);
+    w.u8(match config.layout {Layout::Levels => 0, Layout::Maze => 1});
+    w.opt_u32(config.shrink_seconds);
+    w.opt_u32(config.obstacle_every);
+    w.opt_u32(config.fog_radius.map(|radius| radius as u32));
+    w.opt_u32(config.time_attack_seconds);
+    w.u8(config.endless as u8);
+    w.opt_u32(config.seed);
+}
+
+fn read_config(r: &mut ByteReader) -> Result<GameConfig, DecodeError> {
+    Ok(GameConfig {
+        win_threshold: r.u32()?,
+        food_target: r.u16()? as usize,
+        lives: r.u32()?,
+        speed: r.u16()? as usize,
+        board_mode: match r.u8()? {
+            0 => BoardMode::Walled,
+            1 => BoardMode::Wrapping,
+            b => return Err(DecodeError::BadValue(b))
+        },
+        hunger: r.opt_u32()?,
+        tron: read_bool(r)?,
+        layout: match r.u8()? {
+            0 => Layout::Levels,
+            1 => Layout::Maze,
+            b => return Err(DecodeError::BadValue(b))
+        },
+        shrink_seconds: r.opt_u32()?,
+        obstacle_every: r.opt_u32()?,
+        fog_radius: r.opt_u32()?.map(|radius| radius as usize),
+        time_attack_seconds: r.opt_u32()?,
+        endless: read_bool(r)?,
+        seed: r.opt_u32()?
+    })
+}
+
+// Everything a snapshot holds except the board and the two bodies, which
+// make up most of a game's size.
+struct Staged<const WIDTH: usize, const HEIGHT: usize> {
+    config: GameConfig,
+    cells_at: usize,
+    snake: Snake<WIDTH,HEIGHT>,
+    start: Snake<WIDTH,HEIGHT>,
+    body_at: usize,
+    inputs: InputQueue,
+    pending_growth: u32,
+    status: Status,
+    over_cause: OverCause,
+    difficulty: Difficulty,
+    food_eaten: u32,
+    points: u32,
+    breakdown: ScoreBreakdown,
+    current_level: usize,
+    level_food: u32,
+    level_time_limit: Option<u32>,
+    level_started: u64,
+    lives: u32,
+    invulnerable: u32,
+    hunger_left: u32,
+    spawn_delay: u32,
+    keys_held: u32,
+    bonus: Option<(Position<WIDTH,HEIGHT>, u32)>,
+    effects: [u32; EFFECT_KINDS],
+    mover_countdown: u32,
+    movers: [Option<Mover<WIDTH,HEIGHT>>; MAX_MOVERS],
+    enemies: [Option<Position<WIDTH,HEIGHT>>; MAX_ENEMIES],
+    enemy_turn: bool,
+    wanderers: [Option<Wanderer<WIDTH,HEIGHT>>; MAX_WANDERERS],
+    rival_enabled: bool,
+    two_player: bool,
+    rival_at: usize,
+    winner: Option<Winner>,
+    arena_inset: usize,
+    next_shrink: u64,
+    combo: u32,
+    last_meal: u64,
+    straight_food: u32,
+    run_seed: u32,
+    rng: u64,
+    moves: u32,
+    turns: u32,
+    elapsed_ticks: u64,
+    paused_ticks: u64,
+    countdown: usize,
+}
+
+struct QuickSave {
+    bytes: [u8; QUICK_SAVE_LEN], len: usize
+}
+
+static QUICK_SAVE: Mutex<QuickSave> = Mutex::new(QuickSave {bytes: [0; QUICK_SAVE_LEN], len: 0});
+
+// Only the state a running game needs is kept. Session-wide things (the
+// campaign, achievements, high scores, options set through the setters)
+// and transient ones (popups, the toast, demo and replay state) are left
+// as they are on load.
+impl <const WIDTH: usize, const HEIGHT: usize> SnakeGame<WIDTH,HEIGHT> {
+    pub fn serialize(&self, out: &mut [u8]) -> usize {
+        let mut w = ByteWriter::new(out);
+        w.u8(SNAPSHOT_VERSION);
+        w.u16(WIDTH as u16);
+        w.u16(HEIGHT as u16);
+        write_config(&mut w, &self.config);
+        for p in self.cell_pos_iter() {
+            w.u8(self.cell(p).to_byte());
+        }
+        write_snake(&mut w, &self.snake);
+        write_snake(&mut w, &self.start);
+        write_body(&mut w, &self.body);
+        write_inputs(&mut w, &self.inputs);
+        w.u32(self.pending_growth);
+        w.u8(self.status.to_byte());
+        w.u8(self.over_cause.to_byte());
+        w.u8(self.difficulty.to_byte());
+        w.u32(self.food_eaten);
+        w.u32(self.points);
+        w.u32(self.breakdown.base);
+        w.u32(self.breakdown.zone_bonus);
+        w.u32(self.breakdown.multiplier_bonus);
+        w.u32(self.breakdown.combo_bonus);
+        w.u16(self.current_level as u16);
+        w.u32(self.level_food);
+        w.opt_u32(self.level_time_limit);
+        w.u64(self.level_started);
+        w.u32(self.lives);
+        w.u32(self.invulnerable);
+        w.u32(self.hunger_left);
+        w.u32(self.spawn_delay);
+        w.u32(self.keys_held);
+        w.u8(self.bonus.is_some() as u8);
+        if let Some((p, left)) = self.bonus {
+            write_pos(&mut w, p);
+            w.u32(left);
+        }
+        for effect in ALL_EFFECTS.iter() {
+            w.u32(self.effects.remaining(*effect));
+        }
+        w.u32(self.mover_countdown);
+        for mover in self.movers.iter() {
+            w.u8(mover.is_some() as u8);
+            if let Some(mover) = mover {
+                write_pos(&mut w, mover.lead);
+                w.u16(mover.len as u16);
+                w.u8(mover.dir.to_byte());
+            }
+        }
+        w.u8(self.enemy_positions().count() as u8);
+        for p in self.enemy_positions() {
+            write_pos(&mut w, p);
+        }
+        w.u8(self.enemy_turn as u8);
+        w.u8(self.wanderers.iter().flatten().count() as u8);
+        for wanderer in self.wanderers.iter().flatten() {
+            write_pos(&mut w, wanderer.pos);
+            w.u32(wanderer.cooldown);
+        }
+        w.u8(self.rival_enabled as u8);
+        w.u8(self.two_player as u8);
+        self.write_rival(&mut w);
+        w.u8(self.winner.map_or(u8::MAX, |winner| winner.to_byte()));
+        w.u16(self.arena_inset as u16);
+        w.u64(self.next_shrink);
+        w.u32(self.combo);
+        w.u64(self.last_meal);
+        w.u32(self.straight_food);
+        w.u32(self.run_seed);
+        w.u64(self.rng.state());
+        w.u32(self.moves);
+        w.u32(self.turns);
+        w.u64(self.elapsed_ticks);
+        w.u64(self.paused_ticks);
+        w.u16(self.countdown as u16);
+        w.finish()
+    }
+
+    // Stage maps are taken from the built-in campaign.
+    pub fn deserialize(data: &[u8]) -> Result<Self, DecodeError> {
+        let mut game = Self::new(GameConfig::default());
+        game.restore(data)?;
+        Ok(game)
+    }
+
+    // Nothing changes unless the whole snapshot decodes.
+    pub fn restore(&mut self, data: &[u8]) -> Result<(), DecodeError> {
+        let mut r = ByteReader::new(data);
+        let version = r.u8()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(DecodeError::BadVersion(version));
+        }
+        if r.u16()? as usize != WIDTH || r.u16()? as usize != HEIGHT {
+            return Err(DecodeError::OutOfBounds);
+        }
+        let staged = Self::read_staged(&mut r)?;
+        self.config = staged.config;
+        let mut cells = ByteReader::at(data, staged.cells_at);
+        for p in self.cell_pos_iter() {
+            let (row, col) = p.row_col();
+            self.cells[row][col] = Cell::from_byte(cells.u8()?)?;
+        }
+        self.snake = staged.snake;
+        self.start = staged.start;
+        self.body = read_body(&mut ByteReader::at(data, staged.body_at))?;
+        self.inputs = staged.inputs;
+        self.pending_growth = staged.pending_growth;
+        self.status = staged.status;
+        self.over_cause = staged.over_cause;
+        self.difficulty = staged.difficulty;
+        self.food_eaten = staged.food_eaten;
+        self.points = staged.points;
+        self.breakdown = staged.breakdown;
+        self.current_level = staged.current_level;
+        self.level_food = staged.level_food;
+        self.level_time_limit = staged.level_time_limit;
+        self.level_started = staged.level_started;
+        self.lives = staged.lives;
+        self.invulnerable = staged.invulnerable;
+        self.hunger_left = staged.hunger_left;
+        self.spawn_delay = staged.spawn_delay;
+        self.keys_held = staged.keys_held;
+        self.bonus = staged.bonus;
+        for (effect, left) in ALL_EFFECTS.iter().zip(staged.effects.iter()) {
+            self.effects.set_remaining(*effect, *left);
+        }
+        self.mover_countdown = staged.mover_countdown;
+        self.movers = staged.movers;
+        self.enemies = [None; MAX_ENEMIES];
+        for p in staged.enemies.iter().flatten() {
+            self.add_enemy(*p);
+        }
+        self.enemy_turn = staged.enemy_turn;
+        self.wanderers = staged.wanderers;
+        self.rival_enabled = staged.rival_enabled;
+        self.two_player = staged.two_player;
+        self.rival = Self::read_rival(&mut ByteReader::at(data, staged.rival_at))?;
+        self.winner = staged.winner;
+        self.arena_inset = staged.arena_inset;
+        self.next_shrink = staged.next_shrink;
+        self.combo = staged.combo;
+        self.last_meal = staged.last_meal;
+        self.straight_food = staged.straight_food;
+        self.run_seed = staged.run_seed;
+        self.rng = Rng::new(staged.rng);
+        self.moves = staged.moves;
+        self.turns = staged.turns;
+        self.elapsed_ticks = staged.elapsed_ticks;
+        self.paused_ticks = staged.paused_ticks;
+        self.countdown = staged.countdown;
+        self.recount_food();
+        self.demo = false;
+        self.playback = None;
+        Ok(())
+    }
+
+    // The board and the bodies are checked where they lie and only noted
+    // down; restore reads them again once everything else has decoded.
+    fn read_staged(r: &mut ByteReader) -> Result<Staged<WIDTH,HEIGHT>, DecodeError> {
+        let config = read_config(r)?;
+        let cells_at = r.position();
+        for _ in 0..WIDTH * HEIGHT {
+            Cell::from_byte(r.u8()?)?;
+        }
+        let staged = Staged {
+            config,
+            cells_at,
+            snake: read_snake(r)?,
+            start: read_snake(r)?,
+            body_at: {
+                let at = r.position();
+                let body: Body<WIDTH,HEIGHT> = read_body(r)?;
+                check_body(r, cells_at, body.iter(), Cell::Body)?;
+                at
+            },
+            inputs: read_inputs(r)?,
+            pending_growth: r.u32()?,
+            status: Status::from_byte(r.u8()?)?,
+            over_cause: OverCause::from_byte(r.u8()?)?,
+            difficulty: Difficulty::from_byte(r.u8()?)?,
+            food_eaten: r.u32()?,
+            points: r.u32()?,
+            breakdown: ScoreBreakdown {
+                base: r.u32()?, zone_bonus: r.u32()?, multiplier_bonus: r.u32()?, combo_bonus: r.u32()?
+            },
+            current_level: r.u16()? as usize,
+            level_food: r.u32()?,
+            level_time_limit: r.opt_u32()?,
+            level_started: r.u64()?,
+            lives: r.u32()?,
+            invulnerable: r.u32()?,
+            hunger_left: r.u32()?,
+            spawn_delay: r.u32()?,
+            keys_held: r.u32()?,
+            bonus: if read_bool(r)? {Some((read_pos(r)?, r.u32()?))} else {None},
+            effects: {
+                let mut effects = [0; EFFECT_KINDS];
+                for left in effects.iter_mut() {
+                    *left = r.u32()?;
+                }
+                effects
+            },
+            mover_countdown: r.u32()?,
+            movers: {
+                let mut movers = [None; MAX_MOVERS];
+                for slot in movers.iter_mut() {
+                    *slot = if read_bool(r)? {Some(read_mover(r)?)} else {None};
+                }
+                movers
+            },
+            enemies: {
+                let count = r.u8()? as usize;
+                if count > MAX_ENEMIES {
+                    return Err(DecodeError::OutOfBounds);
+                }
+                let mut enemies = [None; MAX_ENEMIES];
+                for slot in enemies[..count].iter_mut() {
+                    *slot = Some(read_pos(r)?);
+                }
+                enemies
+            },
+            enemy_turn: read_bool(r)?,
+            wanderers: {
+                let count = r.u8()? as usize;
+                if count > MAX_WANDERERS {
+                    return Err(DecodeError::OutOfBounds);
+                }
+                let mut wanderers = [None; MAX_WANDERERS];
+                for slot in wanderers[..count].iter_mut() {
+                    *slot = Some(Wanderer {pos: read_pos(r)?, cooldown: r.u32()?});
+                }
+                wanderers
+            },
+            rival_enabled: read_bool(r)?,
+            two_player: read_bool(r)?,
+            rival_at: {
+                let at = r.position();
+                if let Some(rival) = Self::read_rival(r)? {
+                    check_body(r, cells_at, rival.body_iter(), Cell::RivalBody)?;
+                }
+                at
+            },
+            winner: match r.u8()? {
+                u8::MAX => None,
+                b => Some(Winner::from_byte(b)?)
+            },
+            arena_inset: r.u16()? as usize,
+            next_shrink: r.u64()?,
+            combo: r.u32()?,
+            last_meal: r.u64()?,
+            straight_food: r.u32()?,
+            run_seed: r.u32()?,
+            rng: r.u64()?,
+            moves: r.u32()?,
+            turns: r.u32()?,
+            elapsed_ticks: r.u64()?,
+            paused_ticks: r.u64()?,
+            countdown: r.u16()? as usize
+        };
+        // The game counts these down or subtracts them from the clock
+        // without checking, and never starts a run without a life.
+        if staged.config.lives == 0 || staged.mover_countdown == 0
+            || staged.level_started > staged.elapsed_ticks || staged.last_meal > staged.elapsed_ticks {
+            return Err(DecodeError::Inconsistent);
+        }
+        Ok(staged)
+    }
+
+    pub fn quick_save(&mut self) -> bool {
+        let saved = {
+            let mut slot = QUICK_SAVE.lock();
+            slot.len = self.serialize(&mut slot.bytes);
+            slot.len > 0
+        };
+        self.show_toast(if saved {"Quick-saved"} else {"Quick-save failed"}, QUICK_SAVE_TOAST_FRAMES);
+        saved
+    }
+
+    pub fn quick_load(&mut self) -> bool {
+        let loaded = {
+            let slot = QUICK_SAVE.lock();
+            slot.len > 0 && self.restore(&slot.bytes[..slot.len]).is_ok()
+        };
+        self.show_toast(if loaded {"Quick-loaded"} else {"No quick-save"}, QUICK_SAVE_TOAST_FRAMES);
+        loaded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MapSource;
+
+    type Small = SnakeGame<10,10>;
+
+    const BOARD: &str =
+        "##########
+         #        #
+         #  *     #
+         #        #
+         #   <*   #
+         #        #
+         #     *  #
+         #        #
+         #        #
+         ##########";
+
+    fn small_game() -> Small {
+        let mut game = Small::from_source(MapSource::Text(BOARD), GameConfig {seed: Some(1), ..GameConfig::default()}, &[]);
+        game.start();
+        game.update();
+        game
+    }
+
+    fn snapshot(game: &Small) -> ([u8; QUICK_SAVE_LEN], usize) {
+        let mut buf = [0; QUICK_SAVE_LEN];
+        let len = game.serialize(&mut buf);
+        assert!(len > 0);
+        (buf, len)
+    }
+
+    #[test]
+    fn a_snapshot_decodes_to_the_same_game() {
+        let game = small_game();
+        let (buf, len) = snapshot(&game);
+        let copy = Small::deserialize(&buf[..len]).unwrap();
+        let (again, again_len) = snapshot(&copy);
+        assert_eq!(&again[..again_len], &buf[..len]);
+        assert_eq!(copy.snake_at(), game.snake_at());
+        assert_eq!(copy.length(), game.length());
+        assert_eq!(copy.score(), game.score());
+        assert_eq!(copy.food_count(), game.food_count());
+    }
+
+    #[test]
+    fn a_truncated_snapshot_changes_nothing() {
+        let mut game = small_game();
+        let (buf, len) = snapshot(&game);
+        let mut other = small_game();
+        other.update();
+        let (before, before_len) = snapshot(&other);
+        assert_eq!(other.restore(&buf[..len - 1]), Err(DecodeError::Truncated));
+        assert_eq!(Small::deserialize(&buf[..len / 2]).err(), Some(DecodeError::Truncated));
+        let (after, after_len) = snapshot(&other);
+        assert_eq!(&after[..after_len], &before[..before_len]);
+        assert!(game.restore(&buf[..len]).is_ok());
+    }
+
+    #[test]
+    fn an_unknown_version_is_refused() {
+        let (mut buf, len) = snapshot(&small_game());
+        buf[0] = SNAPSHOT_VERSION + 1;
+        assert_eq!(Small::deserialize(&buf[..len]).err(), Some(DecodeError::BadVersion(SNAPSHOT_VERSION + 1)));
+    }
+
+    #[test]
+    fn movers_have_to_fit_on_the_board() {
+        let mut game = small_game();
+        let lead = Position::new(1, 4);
+        for (len, dir) in [(0, Dir::E), (11, Dir::S), (6, Dir::E)].iter() {
+            game.movers[0] = Some(Mover {lead, len: *len, dir: *dir});
+            let (buf, buf_len) = snapshot(&game);
+            assert_eq!(Small::deserialize(&buf[..buf_len]).err(), Some(DecodeError::OutOfBounds));
+        }
+        game.movers[0] = Some(Mover {lead, len: 5, dir: Dir::E});
+        let (buf, buf_len) = snapshot(&game);
+        assert!(Small::deserialize(&buf[..buf_len]).is_ok());
+    }
+
+    fn same_board(a: &Small, b: &Small) -> bool {
+        a.snake == b.snake && a.body.iter().eq(b.body.iter()) && a.pending_growth == b.pending_growth
+            && a.points == b.points && a.food_eaten == b.food_eaten && a.status == b.status
+            && a.cells == b.cells && a.food_count() == b.food_count()
+    }
+
+    fn apply(prev: &Small, cur: &Small) -> Small {
+        let mut buf = [0; QUICK_SAVE_LEN];
+        let len = Small::diff(prev, cur, &mut buf);
+        assert!(len > 0);
+        let mut next = *prev;
+        next.apply_diff(&buf[..len]).unwrap();
+        next
+    }
+
+    #[test]
+    fn a_diff_takes_the_previous_board_to_the_current_one() {
+        let first = small_game();
+        assert_eq!(first.food_eaten, 1);
+        let mut cur = first;
+        for _ in 0..12 {
+            let prev = cur;
+            cur.update();
+            assert!(same_board(&apply(&prev, &cur), &cur));
+        }
+        assert!(cur.lives < first.lives);
+        assert!(same_board(&apply(&first, &cur), &cur));
+        assert!(same_board(&apply(&cur, &first), &first));
+    }
+
+    fn refusal(game: &Small) -> Option<DecodeError> {
+        let (buf, len) = snapshot(game);
+        Small::deserialize(&buf[..len]).err()
+    }
+
+    #[test]
+    fn counters_that_would_underflow_are_refused() {
+        let game = small_game();
+        assert_eq!(refusal(&game), None);
+        let mut bad = game;
+        bad.mover_countdown = 0;
+        assert_eq!(refusal(&bad), Some(DecodeError::Inconsistent));
+        let mut bad = game;
+        bad.level_started = game.elapsed_ticks + 1;
+        assert_eq!(refusal(&bad), Some(DecodeError::Inconsistent));
+        let mut bad = game;
+        bad.last_meal = game.elapsed_ticks + 1;
+        assert_eq!(refusal(&bad), Some(DecodeError::Inconsistent));
+        let mut bad = game;
+        bad.config.lives = 0;
+        assert_eq!(refusal(&bad), Some(DecodeError::Inconsistent));
+    }
+
+    #[test]
+    fn bodies_have_to_match_the_board() {
+        let mut game = small_game();
+        game.set_rival(true);
+        game.update();
+        game.update();
+        assert_eq!(refusal(&game), None);
+        let segment = game.body.iter().next().unwrap();
+        let (row, col) = segment.row_col();
+        let mut bad = game;
+        bad.cells[row][col] = Cell::Empty;
+        assert_eq!(refusal(&bad), Some(DecodeError::Inconsistent));
+        let segment = game.positions_of(Cell::RivalBody).next().unwrap();
+        let (row, col) = segment.row_col();
+        let mut bad = game;
+        bad.cells[row][col] = Cell::Empty;
+        assert_eq!(refusal(&bad), Some(DecodeError::Inconsistent));
+    }
+}